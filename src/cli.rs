@@ -0,0 +1,68 @@
+use clap::{ArgAction, Parser};
+use std::path::PathBuf;
+
+/// 命令行参数，优先级高于配置文件
+#[derive(Debug, Clone, Parser)]
+#[command(name = "web_server_rust", version, about = "A small asynchronous static file and API server")]
+pub struct Cli {
+    /// TOML 配置文件路径，收到 SIGHUP 时重新读取
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// 监听地址，端口沿用配置文件 [默认: 127.0.0.1]
+    #[arg(short = 'b', long)]
+    pub bind: Option<String>,
+
+    /// 监听端口，地址沿用配置文件 [默认: 50000]
+    #[arg(short, long)]
+    pub port: Option<u16>,
+
+    /// 静态文件根目录，支持 `~` 展开 [默认: ~/Projects/web-client-node]
+    #[arg(short, long)]
+    pub root: Option<String>,
+
+    /// 访问日志文件路径 [默认: access.log]
+    #[arg(short, long)]
+    pub log_file: Option<PathBuf>,
+
+    /// 工作线程数，默认为 CPU 核心数
+    #[arg(short, long)]
+    pub workers: Option<usize>,
+
+    /// 提高控制台输出详细程度，可重复使用
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// 不在控制台输出访问日志
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// 开发模式：监视根目录，文件变化时自动刷新浏览器中打开的页面
+    #[arg(long)]
+    pub dev: bool,
+}
+
+impl Cli {
+    /// 用命令行的地址或端口替换配置的监听地址 `configured` 中对应的部分，未指定的部分保持不变
+    pub fn address(&self, configured: &str) -> String {
+        let (host, port) = configured.rsplit_once(':').unwrap_or((configured, "50000"));
+        let host = match self.bind.as_deref() {
+            Some(bind) if bind.contains(':') && !bind.starts_with('[') => format!("[{}]", bind),
+            Some(bind) => bind.to_string(),
+            None => host.to_string(),
+        };
+        let port = self.port.map_or_else(|| port.to_string(), |port| port.to_string());
+        format!("{}:{}", host, port)
+    }
+
+    /// 控制台日志级别：0 为静默，1 为访问日志，2 及以上包含调试信息；未指定时返回 `None`
+    pub fn verbosity(&self) -> Option<u8> {
+        if self.quiet {
+            Some(0)
+        } else if self.verbose > 0 {
+            Some(1 + self.verbose)
+        } else {
+            None
+        }
+    }
+}
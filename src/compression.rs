@@ -0,0 +1,141 @@
+use crate::config::CompressionConfig;
+use crate::http::{Headers, Response, StatusCode};
+use async_compression::Level;
+use async_compression::tokio::bufread::{BrotliEncoder, GzipEncoder, ZlibEncoder};
+use std::io::Cursor;
+use tokio::io::{AsyncRead, AsyncReadExt, BufReader};
+
+/// 支持的内容编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// 服务器偏好顺序，q 值相同时靠前的优先
+    pub const PREFERENCE: [Encoding; 3] = [Encoding::Brotli, Encoding::Gzip, Encoding::Deflate];
+
+    /// `Content-Encoding` 中的名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// 按 `Accept-Encoding` 的 q 值选择编码，未列出的编码使用 `*` 的 q 值
+///
+/// `offered` 为服务器可提供的编码；都不可接受时返回 `None`，即不编码。
+pub fn negotiate(accept_encoding: Option<&str>, offered: &[Encoding]) -> Option<Encoding> {
+    let accept = accept_encoding?;
+    let mut wildcard = None;
+    let mut weights: Vec<(Encoding, f32)> = Vec::new();
+    for item in accept.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q=").or_else(|| p.trim().strip_prefix("Q=")))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        match coding.as_str() {
+            "*" => wildcard = Some(q),
+            "br" => weights.push((Encoding::Brotli, q)),
+            "gzip" | "x-gzip" => weights.push((Encoding::Gzip, q)),
+            "deflate" => weights.push((Encoding::Deflate, q)),
+            _ => {}
+        }
+    }
+
+    let mut best: Option<(Encoding, f32)> = None;
+    for encoding in Encoding::PREFERENCE.into_iter().filter(|e| offered.contains(e)) {
+        let q = weights
+            .iter()
+            .find(|(e, _)| *e == encoding)
+            .map(|(_, q)| *q)
+            .or(wildcard)
+            .unwrap_or(0.0);
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((encoding, q));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// 请求可接受的编码；范围请求针对未编码的字节，不压缩
+pub fn requested(headers: &Headers) -> Option<Encoding> {
+    if headers.contains("range") {
+        return None;
+    }
+    negotiate(headers.get("accept-encoding"), &Encoding::PREFERENCE)
+}
+
+/// 响应能否压缩：带消息体、不是部分内容、尚未编码、没有 `no-transform`，且类型可压缩
+///
+/// 压缩中间件和静态文件缓存共用这一判断；可以压缩的响应无论是否压缩都应带上 `Vary: Accept-Encoding`。
+pub fn is_transformable(response: &Response, config: &CompressionConfig) -> bool {
+    response.status.allows_body()
+        && response.status != StatusCode::PartialContent
+        && !response.headers.contains("Content-Encoding")
+        && !response.headers.has_token("Cache-Control", "no-transform")
+        && response
+            .headers
+            .get("Content-Type")
+            .is_some_and(|t| is_compressible(t, config))
+}
+
+/// 判断 MIME 类型是否在可压缩列表中，忽略 `charset` 等参数
+pub fn is_compressible(content_type: &str, config: &CompressionConfig) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    config.types.iter().any(|t| match t.strip_suffix("/*") {
+        Some(major) => mime.split('/').next() == Some(major),
+        None => mime == t.to_ascii_lowercase(),
+    })
+}
+
+/// 以流的方式压缩，读取时才进行编码
+pub fn encode_stream<R>(reader: R, encoding: Encoding, config: &CompressionConfig) -> Box<dyn AsyncRead + Send + Unpin>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+{
+    let reader = BufReader::new(reader);
+    match encoding {
+        Encoding::Brotli => Box::new(BrotliEncoder::with_quality(reader, level(config.brotli_level))),
+        Encoding::Gzip => Box::new(GzipEncoder::with_quality(reader, level(config.gzip_level))),
+        Encoding::Deflate => Box::new(ZlibEncoder::with_quality(reader, level(config.deflate_level))),
+    }
+}
+
+/// 压缩内存中的消息体，结果长度已知，可以继续使用 `Content-Length`
+pub async fn encode_bytes(bytes: Vec<u8>, encoding: Encoding, config: &CompressionConfig) -> std::io::Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    encode_stream(Cursor::new(bytes), encoding, config)
+        .read_to_end(&mut encoded)
+        .await?;
+    Ok(encoded)
+}
+
+fn level(level: u32) -> Level {
+    Level::Precise(level as i32)
+}
+
+/// 编码后的表示与原内容不同，ETag 需要附加编码名区分，如 `"abc"` 变为 `"abc-gzip"`
+pub fn etag_with_encoding(etag: &str, encoding: Encoding) -> String {
+    match etag.strip_suffix('"') {
+        Some(opaque) => format!("{}-{}\"", opaque, encoding.as_str()),
+        None => etag.to_string(),
+    }
+}
+
+/// 去掉条件请求头中实体标签的编码后缀，还原为原内容的 ETag；没有变化时返回 `None`
+pub fn strip_encoding(list: &str, encoding: Encoding) -> Option<String> {
+    let suffix = format!("-{}\"", encoding.as_str());
+    if !list.contains(&suffix) {
+        return None;
+    }
+    Some(list.replace(&suffix, "\""))
+}
@@ -0,0 +1,255 @@
+use crate::config::EtagMode;
+use crate::http::{Method, Request};
+use sha2::{Digest, Sha256};
+use std::fs::Metadata;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+/// 资源的验证器，用于条件请求
+#[derive(Debug, Clone, Default)]
+pub struct Validators {
+    /// 带引号的实体标签，弱验证器以 `W/` 开头
+    pub etag: Option<String>,
+    /// 精确到秒的修改时间
+    pub last_modified: Option<SystemTime>,
+}
+
+impl Validators {
+    /// 根据文件元数据生成验证器；`Content` 模式按块读取文件计算哈希，不把整个文件读入内存
+    pub async fn for_file(path: &Path, metadata: &Metadata, mode: EtagMode) -> std::io::Result<Self> {
+        let modified = metadata.modified().ok();
+        let etag = match mode {
+            EtagMode::Metadata => {
+                let nanos = modified
+                    .and_then(|m| m.duration_since(UNIX_EPOCH).ok())
+                    .map_or(0, |d| d.as_nanos());
+                // 大小加纳秒级修改时间足以区分文件的每次改写；
+                // 作为强验证器，If-Range 断点续传才能使用
+                Some(format!("\"{:x}-{:x}\"", metadata.len(), nanos))
+            }
+            EtagMode::Content => Some(content_etag(path).await?),
+            EtagMode::Off => None,
+        };
+        Ok(Self {
+            etag,
+            last_modified: modified.map(truncate_to_seconds),
+        })
+    }
+
+    /// `Last-Modified` 响应头的取值
+    pub fn last_modified_header(&self) -> Option<String> {
+        self.last_modified.map(httpdate::fmt_http_date)
+    }
+}
+
+/// 文件内容 SHA-256 的前 128 位作为强验证器
+async fn content_etag(path: &Path) -> std::io::Result<String> {
+    let mut file = File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    let digest = hasher.finalize();
+    let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("\"{}\"", hex))
+}
+
+/// 条件请求的求值结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precondition {
+    /// 条件满足，正常处理请求
+    Proceed,
+    /// 304 Not Modified
+    NotModified,
+    /// 412 Precondition Failed
+    Failed,
+}
+
+/// 按 RFC 9110 §13.2.2 规定的顺序对条件请求头求值
+pub fn evaluate(request: &Request, validators: &Validators) -> Precondition {
+    let headers = &request.headers;
+    let etag = validators.etag.as_deref();
+
+    if let Some(if_match) = headers.get("if-match") {
+        if !etag_matches(if_match, etag, true) {
+            return Precondition::Failed;
+        }
+    } else if let Some(since) = headers.get("if-unmodified-since").and_then(parse_date)
+        && validators.last_modified.is_some_and(|m| m > since)
+    {
+        return Precondition::Failed;
+    }
+
+    let safe = matches!(request.method, Method::Get | Method::Head);
+    if let Some(if_none_match) = headers.get("if-none-match") {
+        if etag_matches(if_none_match, etag, false) {
+            return if safe {
+                Precondition::NotModified
+            } else {
+                Precondition::Failed
+            };
+        }
+    } else if let Some(since) = headers.get("if-modified-since").and_then(parse_date)
+        && safe
+        && validators.last_modified.is_some_and(|m| m <= since)
+    {
+        return Precondition::NotModified;
+    }
+    Precondition::Proceed
+}
+
+/// 判断 `If-Match` / `If-None-Match` 列表是否与当前 ETag 匹配
+///
+/// `strong` 为真时使用强比较：弱验证器永不匹配。
+pub fn etag_matches(list: &str, etag: Option<&str>, strong: bool) -> bool {
+    // `*` 匹配任何现有的表示，即使没有生成 ETag
+    if list.trim() == "*" {
+        return true;
+    }
+    let Some(etag) = etag else {
+        return false;
+    };
+    let (current_weak, current) = split_weak(etag);
+    if strong && current_weak {
+        return false;
+    }
+    parse_etag_list(list).into_iter().any(|candidate| {
+        let (weak, opaque) = split_weak(candidate);
+        opaque == current && !(strong && weak)
+    })
+}
+
+/// 拆分逗号分隔的实体标签列表；引号内的逗号属于标签本身
+fn parse_etag_list(list: &str) -> Vec<&str> {
+    let mut tags = Vec::new();
+    let mut rest = list;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        if rest.is_empty() {
+            break;
+        }
+        let start = if rest.starts_with("W/") { 2 } else { 0 };
+        if !rest[start..].starts_with('"') {
+            break;
+        }
+        let Some(end) = rest[start + 1..].find('"') else {
+            break;
+        };
+        let len = start + 1 + end + 1;
+        tags.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    tags
+}
+
+fn split_weak(tag: &str) -> (bool, &str) {
+    match tag.strip_prefix("W/") {
+        Some(opaque) => (true, opaque),
+        None => (false, tag),
+    }
+}
+
+/// 解析 IMF-fixdate 以及 RFC 850、asctime 两种过时格式，无效日期视为不存在
+fn parse_date(value: &str) -> Option<SystemTime> {
+    httpdate::parse_http_date(value.trim()).ok()
+}
+
+/// HTTP 日期只精确到秒，比较前需要去掉亚秒部分
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => UNIX_EPOCH + Duration::from_secs(d.as_secs()),
+        Err(_) => time,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Headers, RequestLine};
+
+    fn validators(etag: Option<&str>) -> Validators {
+        Validators {
+            etag: etag.map(str::to_string),
+            last_modified: Some(httpdate::parse_http_date("Wed, 01 Jan 2025 00:00:00 GMT").unwrap()),
+        }
+    }
+
+    fn check(method: &str, headers: &[(&str, &str)], validators: &Validators) -> Precondition {
+        let mut request_headers = Headers::new();
+        for (name, value) in headers {
+            request_headers.append(name, value);
+        }
+        let line = RequestLine::parse(&format!("{} / HTTP/1.1", method)).unwrap();
+        evaluate(&line.into_request(request_headers, "127.0.0.1:1".parse().unwrap(), Vec::new()), validators)
+    }
+
+    const BEFORE: &str = "Tue, 31 Dec 2024 23:59:59 GMT";
+    const EXACT: &str = "Wed, 01 Jan 2025 00:00:00 GMT";
+    const AFTER: &str = "Wed, 01 Jan 2025 00:00:01 GMT";
+
+    #[test]
+    fn if_match_uses_strong_comparison() {
+        let strong = validators(Some("\"abc\""));
+        assert_eq!(check("GET", &[], &strong), Precondition::Proceed);
+        assert_eq!(check("PUT", &[("if-match", "\"abc\"")], &strong), Precondition::Proceed);
+        assert_eq!(check("PUT", &[("if-match", "\"x\", \"abc\"")], &strong), Precondition::Proceed);
+        assert_eq!(check("PUT", &[("if-match", "\"x\"")], &strong), Precondition::Failed);
+        assert_eq!(check("PUT", &[("if-match", "W/\"abc\"")], &strong), Precondition::Failed);
+        let weak = validators(Some("W/\"abc\""));
+        assert_eq!(check("PUT", &[("if-match", "\"abc\"")], &weak), Precondition::Failed);
+        assert_eq!(check("PUT", &[("if-match", "W/\"abc\"")], &weak), Precondition::Failed);
+    }
+
+    #[test]
+    fn star_matches_any_current_representation() {
+        for etag in [Some("\"abc\""), Some("W/\"abc\""), None] {
+            let v = validators(etag);
+            assert_eq!(check("PUT", &[("if-match", "*")], &v), Precondition::Proceed, "{:?}", etag);
+            assert_eq!(check("GET", &[("if-none-match", "*")], &v), Precondition::NotModified, "{:?}", etag);
+            assert_eq!(check("PUT", &[("if-none-match", "*")], &v), Precondition::Failed, "{:?}", etag);
+        }
+    }
+
+    #[test]
+    fn if_unmodified_since_applies_only_without_if_match() {
+        let v = validators(Some("\"abc\""));
+        assert_eq!(check("PUT", &[("if-unmodified-since", BEFORE)], &v), Precondition::Failed);
+        assert_eq!(check("PUT", &[("if-unmodified-since", EXACT)], &v), Precondition::Proceed);
+        assert_eq!(check("PUT", &[("if-unmodified-since", "not a date")], &v), Precondition::Proceed);
+        let headers = [("if-match", "\"abc\""), ("if-unmodified-since", BEFORE)];
+        assert_eq!(check("PUT", &headers, &v), Precondition::Proceed);
+        // If-Match 失败时不再检查 If-None-Match
+        let headers = [("if-match", "\"x\""), ("if-none-match", "\"abc\"")];
+        assert_eq!(check("GET", &headers, &v), Precondition::Failed);
+    }
+
+    #[test]
+    fn if_none_match_uses_weak_comparison() {
+        let v = validators(Some("\"abc\""));
+        assert_eq!(check("GET", &[("if-none-match", "W/\"abc\"")], &v), Precondition::NotModified);
+        assert_eq!(check("HEAD", &[("if-none-match", "\"abc\"")], &v), Precondition::NotModified);
+        assert_eq!(check("GET", &[("if-none-match", "\"x\"")], &v), Precondition::Proceed);
+        assert_eq!(check("POST", &[("if-none-match", "\"abc\"")], &v), Precondition::Failed);
+        let weak = validators(Some("W/\"abc\""));
+        assert_eq!(check("GET", &[("if-none-match", "\"abc\"")], &weak), Precondition::NotModified);
+    }
+
+    #[test]
+    fn if_modified_since_applies_only_to_safe_requests_without_if_none_match() {
+        let v = validators(Some("\"abc\""));
+        assert_eq!(check("GET", &[("if-modified-since", EXACT)], &v), Precondition::NotModified);
+        assert_eq!(check("GET", &[("if-modified-since", AFTER)], &v), Precondition::NotModified);
+        assert_eq!(check("GET", &[("if-modified-since", BEFORE)], &v), Precondition::Proceed);
+        assert_eq!(check("POST", &[("if-modified-since", AFTER)], &v), Precondition::Proceed);
+        // ETag 不匹配时忽略 If-Modified-Since
+        let headers = [("if-none-match", "\"x\""), ("if-modified-since", AFTER)];
+        assert_eq!(check("GET", &headers, &v), Precondition::Proceed);
+    }
+}
@@ -0,0 +1,715 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// 配置文件内容
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub log: LogConfig,
+    pub limits: LimitsConfig,
+    /// 扩展名到 MIME 类型的覆盖表，例如 `wasm = "application/wasm"`
+    pub mime: HashMap<String, String>,
+    /// 扩展名到 `Cache-Control` 取值的缓存策略表
+    pub cache: HashMap<String, String>,
+    /// API 路由表，为空时使用内置路由
+    pub routes: Vec<RouteConfig>,
+    /// 跨域资源共享策略，缺省不发送 CORS 响应头
+    pub cors: Option<CorsConfig>,
+    /// 按 URL 路径设置的目录选项，子目录继承最近的上级设置
+    pub directories: HashMap<String, DirectoryConfig>,
+    pub compression: CompressionConfig,
+    /// 按状态码配置的错误文档，键为 `404` 这样的状态码或 `4xx`、`5xx`
+    pub errors: HashMap<String, ErrorPageConfig>,
+    pub file_cache: FileCacheConfig,
+    /// 按 `Host` 请求头区分的虚拟主机，未匹配的请求使用顶层配置
+    pub hosts: HashMap<String, HostConfig>,
+    /// HTTPS 监听，缺省不启用
+    pub tls: Option<TlsConfig>,
+}
+
+/// 监听与静态文件相关配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// 监听的套接字地址列表
+    pub listen: Vec<String>,
+    /// 静态文件根目录，支持 `~` 展开
+    pub root: PathBuf,
+    /// 工作线程数，缺省为 CPU 核心数
+    pub workers: Option<usize>,
+    /// 静态文件 ETag 的生成方式
+    pub etag: EtagMode,
+    /// 没有扩展名的文件按内容判断 MIME 类型
+    pub sniff_mime: bool,
+    /// 开发模式，向 HTML 页面注入自动刷新脚本
+    pub dev: bool,
+}
+
+/// ETag 生成方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EtagMode {
+    /// 由文件大小和修改时间生成强验证器
+    #[default]
+    Metadata,
+    /// 由文件内容的 SHA-256 生成强验证器
+    Content,
+    /// 不发送 ETag，只依赖 Last-Modified
+    Off,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: vec!["127.0.0.1:50000".to_string()],
+            root: PathBuf::from("~/Projects/web-client-node"),
+            workers: None,
+            etag: EtagMode::default(),
+            sniff_mime: true,
+            dev: false,
+        }
+    }
+}
+
+/// 日志相关配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// 访问日志文件路径
+    pub file: PathBuf,
+    /// 控制台日志级别：0 为静默，1 为访问日志，2 及以上包含调试信息
+    pub verbosity: u8,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::from("access.log"),
+            verbosity: 1,
+        }
+    }
+}
+
+/// 请求限制
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// 请求体最大字节数
+    pub max_body_size: usize,
+    /// 持久连接的空闲超时（秒）
+    pub keep_alive_timeout: u64,
+    /// 单个连接最多处理的请求数
+    pub max_requests: usize,
+    /// 收到请求行后读完请求头和请求体的最长时间（秒）
+    pub request_timeout: u64,
+    /// 请求行和单个请求头行的最大字节数
+    pub max_line_size: usize,
+    /// 单个请求最多的请求头字段数
+    pub max_headers: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_body_size: 1024 * 1024,
+            keep_alive_timeout: 5,
+            max_requests: 100,
+            request_timeout: 30,
+            max_line_size: 8192,
+            max_headers: 100,
+        }
+    }
+}
+
+/// 单条 API 路由
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    pub method: String,
+    pub path: String,
+    /// 内置处理函数名称，见 [`crate::handlers::API_HANDLERS`]
+    pub handler: String,
+    /// 该路由的请求体上限，缺省使用 `limits.max_body_size`
+    #[serde(default)]
+    pub max_body_size: Option<usize>,
+}
+
+/// 虚拟主机，未设置的项沿用顶层配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HostConfig {
+    /// 静态文件根目录，支持 `~` 展开
+    pub root: PathBuf,
+    /// API 路由表，缺省沿用顶层 `routes`
+    #[serde(default)]
+    pub routes: Option<Vec<RouteConfig>>,
+    /// 错误文档，相对路径相对于本主机的根目录
+    #[serde(default)]
+    pub errors: Option<HashMap<String, ErrorPageConfig>>,
+    /// 访问日志文件，缺省写入 `log.file`
+    #[serde(default)]
+    pub log_file: Option<PathBuf>,
+    /// 按 SNI 为本主机选择的证书，缺省使用 `tls` 中的证书
+    #[serde(default)]
+    pub tls: Option<CertificateConfig>,
+}
+
+/// HTTPS 监听与默认证书
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// HTTPS 监听的套接字地址列表，不能与 `server.listen` 重复
+    pub listen: Vec<String>,
+    /// 未按 SNI 匹配到虚拟主机时使用的 PEM 证书链，支持 `~` 展开
+    pub cert: PathBuf,
+    /// 与 `cert` 对应的 PEM 私钥
+    pub key: PathBuf,
+    /// 检查证书文件是否更新的间隔（秒），0 表示只在收到 SIGHUP 时重新读取
+    #[serde(default = "default_certificate_check_interval")]
+    pub check_interval: u64,
+}
+
+fn default_certificate_check_interval() -> u64 {
+    60
+}
+
+/// PEM 证书链和私钥文件，支持 `~` 展开
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CertificateConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// 单个目录的选项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DirectoryConfig {
+    /// 目录中没有 `index.html` 时是否生成目录列表
+    pub listing: bool,
+    /// 单页应用模式：目录下不存在且没有扩展名的路径返回该目录的 `index.html`
+    pub spa: bool,
+}
+
+/// 单个状态码的错误文档，相对路径相对于 `server.root`
+///
+/// 文档中的 `{{status}}`、`{{title}}`、`{{detail}}` 会替换为具体的错误信息。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ErrorPageConfig {
+    /// 浏览器等接受 HTML 的客户端收到的页面
+    pub html: Option<PathBuf>,
+    /// 接受 JSON 的客户端收到的问题详情（RFC 9457）
+    pub json: Option<PathBuf>,
+}
+
+/// 响应压缩
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompressionConfig {
+    /// 是否实时压缩响应
+    pub enabled: bool,
+    /// 小于该字节数的响应不压缩；长度未知的流式响应总是压缩
+    pub min_size: u64,
+    /// gzip 压缩级别，1–9
+    pub gzip_level: u32,
+    /// deflate 压缩级别，1–9
+    pub deflate_level: u32,
+    /// Brotli 压缩级别，0–11；实时压缩时较高的级别开销很大
+    pub brotli_level: u32,
+    /// 可压缩的 MIME 类型，`text/*` 匹配同一大类
+    pub types: Vec<String>,
+    /// 优先发送构建时生成的 `.br`、`.gz` 同名文件，不受 `enabled` 影响
+    pub precompressed: bool,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: 1024,
+            gzip_level: 6,
+            deflate_level: 6,
+            brotli_level: 4,
+            types: [
+                "text/*",
+                "application/javascript",
+                "application/json",
+                "application/xml",
+                "application/wasm",
+                "image/svg+xml",
+            ]
+            .map(String::from)
+            .to_vec(),
+            precompressed: true,
+        }
+    }
+}
+
+/// 静态文件内存缓存
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileCacheConfig {
+    pub enabled: bool,
+    /// 缓存占用的总字节数上限，包括压缩版本
+    pub max_size: u64,
+    /// 超过该字节数的文件不缓存，直接从磁盘发送
+    pub max_file_size: u64,
+}
+
+impl Default for FileCacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_size: 64 * 1024 * 1024,
+            max_file_size: 256 * 1024,
+        }
+    }
+}
+
+/// 跨域资源共享策略
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorsConfig {
+    /// 允许的来源，如 `https://app.example.com`，`*` 表示任意来源
+    pub allow_origins: Vec<String>,
+    /// 是否允许携带凭据（Cookie、认证头）
+    #[serde(default)]
+    pub allow_credentials: bool,
+    /// 暴露给脚本的响应头
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+    /// 预检结果的缓存时间（秒）
+    #[serde(default = "default_cors_max_age")]
+    pub max_age: u64,
+}
+
+fn default_cors_max_age() -> u64 {
+    600
+}
+
+/// 配置加载错误
+#[derive(Debug)]
+pub enum ConfigError {
+    /// 无法读取配置文件
+    Io(PathBuf, std::io::Error),
+    /// TOML 语法或类型错误
+    Parse(PathBuf, toml::de::Error),
+    /// 语义校验失败，`key` 指向出错的配置项
+    Invalid { key: String, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "cannot read {}: {}", path.display(), e),
+            ConfigError::Parse(path, e) => write!(f, "invalid config file {}: {}", path.display(), e),
+            ConfigError::Invalid { key, message } => write!(f, "invalid value for `{}`: {}", key, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+fn invalid(key: impl Into<String>, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.into(),
+        message: message.into(),
+    }
+}
+
+/// 配置来源：可选的配置文件以及优先级更高的命令行参数
+#[derive(Debug, Clone)]
+pub struct ConfigSource {
+    cli: crate::cli::Cli,
+}
+
+impl ConfigSource {
+    pub fn new(cli: crate::cli::Cli) -> Self {
+        Self { cli }
+    }
+
+    /// 读取配置文件，叠加命令行参数并校验
+    pub fn load(&self) -> Result<Config, ConfigError> {
+        let mut config = match &self.cli.config {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        self.apply_cli(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_cli(&self, config: &mut Config) {
+        let cli = &self.cli;
+        if cli.bind.is_some() || cli.port.is_some() {
+            if config.server.listen.is_empty() {
+                config.server.listen = ServerConfig::default().listen;
+            }
+            let mut listen: Vec<String> = Vec::new();
+            for address in config.server.listen.iter().map(|a| cli.address(a)) {
+                if !listen.contains(&address) {
+                    listen.push(address);
+                }
+            }
+            config.server.listen = listen;
+        }
+        if let Some(root) = &cli.root {
+            config.server.root = PathBuf::from(root);
+        }
+        if let Some(workers) = cli.workers {
+            config.server.workers = Some(workers);
+        }
+        if let Some(file) = &cli.log_file {
+            config.log.file = file.clone();
+        }
+        if let Some(verbosity) = cli.verbosity() {
+            config.log.verbosity = verbosity;
+        }
+        if cli.dev {
+            config.server.dev = true;
+        }
+    }
+}
+
+impl Config {
+    /// 从 TOML 文件解析配置（不做语义校验）
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    /// 校验配置，并将根目录中的 `~` 展开
+    pub fn validate(&mut self) -> Result<(), ConfigError> {
+        if self.server.listen.is_empty() {
+            return Err(invalid("server.listen", "at least one address is required"));
+        }
+        for (i, address) in self.server.listen.iter().enumerate() {
+            if address.parse::<SocketAddr>().is_err() {
+                return Err(invalid(
+                    format!("server.listen[{}]", i),
+                    format!("`{}` is not a socket address like 127.0.0.1:8080", address),
+                ));
+            }
+        }
+
+        validate_root("server.root", &mut self.server.root)?;
+
+        if self.server.workers == Some(0) {
+            return Err(invalid("server.workers", "must be greater than 0"));
+        }
+        if self.log.file.as_os_str().is_empty() {
+            return Err(invalid("log.file", "must not be empty"));
+        }
+        if self.limits.max_body_size == 0 {
+            return Err(invalid("limits.max_body_size", "must be greater than 0"));
+        }
+        if self.limits.keep_alive_timeout == 0 {
+            return Err(invalid("limits.keep_alive_timeout", "must be greater than 0"));
+        }
+        if self.limits.max_requests == 0 {
+            return Err(invalid("limits.max_requests", "must be greater than 0"));
+        }
+        if self.limits.request_timeout == 0 {
+            return Err(invalid("limits.request_timeout", "must be greater than 0"));
+        }
+        if self.limits.max_line_size == 0 {
+            return Err(invalid("limits.max_line_size", "must be greater than 0"));
+        }
+        if self.limits.max_headers == 0 {
+            return Err(invalid("limits.max_headers", "must be greater than 0"));
+        }
+
+        // 扩展名不区分大小写，统一按小写查找
+        self.mime = self.mime.drain().map(|(ext, mime)| (ext.to_ascii_lowercase(), mime)).collect();
+        for (ext, mime) in &self.mime {
+            if ext.is_empty() || ext.starts_with('.') {
+                return Err(invalid(
+                    format!("mime.{}", ext),
+                    "extensions are written without a leading dot",
+                ));
+            }
+            if !mime.contains('/') || !is_header_safe(mime) {
+                return Err(invalid(format!("mime.{}", ext), format!("`{}` is not a MIME type", mime)));
+            }
+        }
+        self.cache = self.cache.drain().map(|(ext, policy)| (ext.to_ascii_lowercase(), policy)).collect();
+        for (ext, policy) in &self.cache {
+            if policy.is_empty() || !is_header_safe(policy) {
+                return Err(invalid(
+                    format!("cache.{}", ext),
+                    "must be a non-empty Cache-Control value on a single line",
+                ));
+            }
+        }
+
+        validate_routes("routes", &mut self.routes)?;
+
+        let mut directories = HashMap::new();
+        for (path, directory) in self.directories.drain() {
+            if !path.starts_with('/') || path.split('/').any(|s| s == "." || s == "..") {
+                return Err(invalid(
+                    format!("directories.\"{}\"", path),
+                    "must be an absolute URL path such as `/downloads`",
+                ));
+            }
+            // 统一去掉末尾斜杠，`/downloads/` 与 `/downloads` 等价
+            let key = match path.trim_end_matches('/') {
+                "" => "/".to_string(),
+                trimmed => trimmed.to_string(),
+            };
+            if directories.insert(key, directory).is_some() {
+                return Err(invalid(format!("directories.\"{}\"", path), "is configured twice"));
+            }
+        }
+        self.directories = directories;
+
+        validate_errors("errors", &mut self.errors, &self.server.root)?;
+
+        let compression = &self.compression;
+        if !(1..=9).contains(&compression.gzip_level) {
+            return Err(invalid("compression.gzip_level", "must be between 1 and 9"));
+        }
+        if !(1..=9).contains(&compression.deflate_level) {
+            return Err(invalid("compression.deflate_level", "must be between 1 and 9"));
+        }
+        if compression.brotli_level > 11 {
+            return Err(invalid("compression.brotli_level", "must be between 0 and 11"));
+        }
+        for (i, mime) in compression.types.iter().enumerate() {
+            if !mime.contains('/') || !is_header_safe(mime) {
+                return Err(invalid(
+                    format!("compression.types[{}]", i),
+                    format!("`{}` is not a MIME type", mime),
+                ));
+            }
+        }
+
+        let mut hosts = HashMap::new();
+        for (name, mut host) in self.hosts.drain() {
+            let key = format!("hosts.\"{}\"", name);
+            let pattern = name.trim_end_matches('.').to_ascii_lowercase();
+            if !is_host_pattern(&pattern) {
+                return Err(invalid(
+                    key,
+                    "must be a host name such as `example.com` or a wildcard such as `*.example.com`",
+                ));
+            }
+            validate_root(&format!("{}.root", key), &mut host.root)?;
+            if let Some(routes) = &mut host.routes {
+                validate_routes(&format!("{}.routes", key), routes)?;
+            }
+            if let Some(errors) = &mut host.errors {
+                validate_errors(&format!("{}.errors", key), errors, &host.root)?;
+            }
+            if let Some(file) = &host.log_file
+                && file.as_os_str().is_empty()
+            {
+                return Err(invalid(format!("{}.log_file", key), "must not be empty"));
+            }
+            if let Some(certificate) = &mut host.tls {
+                if self.tls.is_none() {
+                    return Err(invalid(format!("{}.tls", key), "requires a top-level [tls] section"));
+                }
+                let key = format!("{}.tls", key);
+                validate_certificate(&key, &mut certificate.cert, &mut certificate.key)?;
+            }
+            if hosts.insert(pattern, host).is_some() {
+                return Err(invalid(key, "is configured twice"));
+            }
+        }
+        self.hosts = hosts;
+
+        if let Some(tls) = &mut self.tls {
+            if tls.listen.is_empty() {
+                return Err(invalid("tls.listen", "at least one address is required"));
+            }
+            for (i, address) in tls.listen.iter().enumerate() {
+                if address.parse::<SocketAddr>().is_err() {
+                    return Err(invalid(
+                        format!("tls.listen[{}]", i),
+                        format!("`{}` is not a socket address like 0.0.0.0:8443", address),
+                    ));
+                }
+                if self.server.listen.contains(address) {
+                    return Err(invalid(
+                        format!("tls.listen[{}]", i),
+                        format!("`{}` is already used by server.listen", address),
+                    ));
+                }
+            }
+            validate_certificate("tls", &mut tls.cert, &mut tls.key)?;
+        }
+
+        if self.file_cache.max_file_size > self.file_cache.max_size {
+            return Err(invalid("file_cache.max_file_size", "must not exceed file_cache.max_size"));
+        }
+
+        if let Some(cors) = &self.cors {
+            if cors.allow_origins.is_empty() {
+                return Err(invalid("cors.allow_origins", "must list at least one origin"));
+            }
+            for (i, origin) in cors.allow_origins.iter().enumerate() {
+                if origin != "*" && (!origin.contains("://") || origin.ends_with('/') || !is_header_safe(origin)) {
+                    return Err(invalid(
+                        format!("cors.allow_origins[{}]", i),
+                        format!("`{}` is not an origin such as `https://example.com`", origin),
+                    ));
+                }
+            }
+            for (i, header) in cors.expose_headers.iter().enumerate() {
+                if header.is_empty() || !header.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                    return Err(invalid(
+                        format!("cors.expose_headers[{}]", i),
+                        format!("`{}` is not a header name", header),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 查找 URL 路径所在目录的选项，返回最近的已配置上级目录及其选项
+    pub fn directory(&self, path: &str) -> Option<(&str, &DirectoryConfig)> {
+        let mut current = path.trim_end_matches('/');
+        loop {
+            let key = if current.is_empty() { "/" } else { current };
+            if let Some((prefix, directory)) = self.directories.get_key_value(key) {
+                return Some((prefix, directory));
+            }
+            match current.rfind('/') {
+                Some(i) if !current.is_empty() => current = &current[..i],
+                _ => return None,
+            }
+        }
+    }
+
+    /// 虚拟主机的完整配置：以顶层配置为基础，替换该主机单独设置的项
+    pub fn for_host(&self, host: &HostConfig) -> Config {
+        let mut config = Config {
+            hosts: HashMap::new(),
+            ..self.clone()
+        };
+        config.server.root = host.root.clone();
+        if let Some(routes) = &host.routes {
+            config.routes = routes.clone();
+        }
+        if let Some(errors) = &host.errors {
+            config.errors = errors.clone();
+        }
+        if let Some(file) = &host.log_file {
+            config.log.file = file.clone();
+        }
+        config
+    }
+
+    /// 当前配置对应的日志设置
+    pub fn log_settings(&self) -> crate::utils::LogSettings {
+        crate::utils::LogSettings {
+            file: self.log.file.clone(),
+            verbosity: self.log.verbosity,
+        }
+    }
+}
+
+fn validate_root(key: &str, root: &mut PathBuf) -> Result<(), ConfigError> {
+    *root = crate::utils::expand_home(root);
+    if !root.is_dir() {
+        return Err(invalid(key, format!("{} is not a directory", root.display())));
+    }
+    Ok(())
+}
+
+fn validate_routes(key: &str, routes: &mut [RouteConfig]) -> Result<(), ConfigError> {
+    for (i, route) in routes.iter_mut().enumerate() {
+        route.method = route.method.to_ascii_uppercase();
+        if !matches!(route.method.as_str(), "GET" | "POST" | "PUT" | "DELETE" | "PATCH") {
+            return Err(invalid(
+                format!("{}[{}].method", key, i),
+                format!("unsupported method `{}`", route.method),
+            ));
+        }
+        if let Err(e) = crate::router::Pattern::parse(&route.path) {
+            return Err(invalid(format!("{}[{}].path", key, i), e));
+        }
+        if route.max_body_size == Some(0) {
+            return Err(invalid(format!("{}[{}].max_body_size", key, i), "must be greater than 0"));
+        }
+        if !crate::handlers::API_HANDLERS.contains(&route.handler.as_str()) {
+            return Err(invalid(
+                format!("{}[{}].handler", key, i),
+                format!(
+                    "unknown handler `{}`, expected one of: {}",
+                    route.handler,
+                    crate::handlers::API_HANDLERS.join(", ")
+                ),
+            ));
+        }
+    }
+    for (i, route) in routes.iter().enumerate() {
+        if routes[..i]
+            .iter()
+            .any(|r| r.method == route.method && r.path == route.path)
+        {
+            return Err(invalid(
+                format!("{}[{}]", key, i),
+                format!("duplicate route {} {}", route.method, route.path),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 校验错误文档的状态码，并把相对路径解析到 `root` 之下
+fn validate_errors(key: &str, errors: &mut HashMap<String, ErrorPageConfig>, root: &Path) -> Result<(), ConfigError> {
+    for (status, page) in errors {
+        let valid = matches!(
+            status.as_bytes(),
+            [b'4' | b'5', b'x', b'x'] | [b'4' | b'5', b'0'..=b'9', b'0'..=b'9']
+        );
+        if !valid {
+            return Err(invalid(
+                format!("{}.\"{}\"", key, status),
+                "must be a 4xx or 5xx status code such as `404`, or a class such as `5xx`",
+            ));
+        }
+        for (format, path) in [("html", &mut page.html), ("json", &mut page.json)] {
+            let Some(path) = path else { continue };
+            *path = root.join(crate::utils::expand_home(path));
+            if !path.is_file() {
+                return Err(invalid(
+                    format!("{}.\"{}\".{}", key, status, format),
+                    format!("{} is not a file", path.display()),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_certificate(key: &str, cert: &mut PathBuf, private_key: &mut PathBuf) -> Result<(), ConfigError> {
+    for (name, path) in [("cert", cert), ("key", private_key)] {
+        *path = crate::utils::expand_home(path);
+        if !path.is_file() {
+            return Err(invalid(format!("{}.{}", key, name), format!("{} is not a file", path.display())));
+        }
+    }
+    Ok(())
+}
+
+/// 主机名或 `*.` 开头的通配符，由字母、数字、`-` 和 `.` 组成
+fn is_host_pattern(pattern: &str) -> bool {
+    let name = pattern.strip_prefix("*.").unwrap_or(pattern);
+    !name.is_empty()
+        && name.split('.').all(|label| {
+            !label.is_empty() && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
+
+fn is_header_safe(value: &str) -> bool {
+    !value.contains(['\r', '\n'])
+}
@@ -0,0 +1,170 @@
+use crate::config::Config;
+use crate::http::{Body, Response};
+use std::collections::HashMap;
+
+/// 错误响应的表示形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Html,
+    Json,
+}
+
+/// 配置的错误文档，构建站点时读入内存，重新加载配置时随之刷新
+#[derive(Debug, Default)]
+pub struct ErrorPages {
+    /// 键为状态码或 `4xx`、`5xx`
+    pages: HashMap<String, Documents>,
+}
+
+#[derive(Debug, Default)]
+struct Documents {
+    html: Option<String>,
+    json: Option<String>,
+}
+
+impl ErrorPages {
+    /// 读取配置中的错误文档，读取失败的文档退回内置页面
+    pub fn load(config: &Config) -> Self {
+        let read = |path: &Option<std::path::PathBuf>| {
+            let path = path.as_ref()?;
+            std::fs::read_to_string(path)
+                .map_err(|e| eprintln!("Cannot read error page {}: {}", path.display(), e))
+                .ok()
+        };
+        let pages = config
+            .errors
+            .iter()
+            .map(|(status, page)| {
+                let documents = Documents {
+                    html: read(&page.html),
+                    json: read(&page.json),
+                };
+                (status.clone(), documents)
+            })
+            .collect();
+        Self { pages }
+    }
+
+    /// 按 `Accept` 把 [`Response::error`] 生成的纯文本错误替换为 HTML 或 JSON 文档
+    ///
+    /// 处理函数自行构造的消息体保持不变；`instance` 为请求路径，写入问题详情。
+    pub fn render(&self, mut response: Response, accept: Option<&str>, instance: &str) -> Response {
+        let Some(detail) = response.error_detail().map(str::to_string) else {
+            return response;
+        };
+        if response.headers.contains("Content-Encoding") {
+            return response;
+        }
+        let format = negotiate(accept);
+        if !response.headers.has_token("Vary", "Accept") {
+            response.headers.append("Vary", "Accept");
+        }
+
+        let status = response.status;
+        let title = status.reason_phrase();
+        let code = status.as_u16().to_string();
+        let class = format!("{}xx", &code[..1]);
+        // 状态码没有配置某种格式时，再查找同类状态码的文档
+        let document = |pick: fn(&Documents) -> Option<&str>| {
+            [&code, &class].into_iter().find_map(|key| self.pages.get(key).and_then(pick))
+        };
+        let (content_type, body) = match format {
+            Format::Text => return response,
+            Format::Html => {
+                let detail = crate::listing::html_escape(&detail);
+                let body = match document(|d| d.html.as_deref()) {
+                    Some(template) => fill(template, &code, title, &detail),
+                    None => builtin_html(&code, title, &detail),
+                };
+                ("text/html; charset=utf-8", body)
+            }
+            Format::Json => {
+                let body = match document(|d| d.json.as_deref()) {
+                    Some(template) => fill(template, &code, &json_escape(title), &json_escape(&detail)),
+                    None => serde_json::json!({
+                        "type": "about:blank",
+                        "title": title,
+                        "status": status.as_u16(),
+                        "detail": detail,
+                        "instance": instance,
+                    })
+                    .to_string(),
+                };
+                ("application/problem+json", body)
+            }
+        };
+        response.headers.insert("Content-Type", content_type);
+        // 直接替换消息体，HEAD 响应仍只发送头部
+        response.body = Body::Bytes(body.into_bytes());
+        response
+    }
+}
+
+/// 比较 `Accept` 中 HTML、JSON 与纯文本的 q 值；明确列出的类型优先于通配符，
+/// 完全相同时保持纯文本，因此 `*/*` 或缺少 `Accept` 的客户端收到原来的响应
+fn negotiate(accept: Option<&str>) -> Format {
+    let Some(accept) = accept else {
+        return Format::Text;
+    };
+    let mut best = (Format::Text, weight(accept, Format::Text));
+    for format in [Format::Html, Format::Json] {
+        let w = weight(accept, format);
+        if w > best.1 {
+            best = (format, w);
+        }
+    }
+    best.0
+}
+
+/// 某种表示形式在 `Accept` 中的权重：(q 值, 是否明确列出)
+fn weight(accept: &str, format: Format) -> (f32, bool) {
+    let (major, exact): (&str, &[&str]) = match format {
+        Format::Text => ("text", &["text/plain"]),
+        Format::Html => ("text", &["text/html", "application/xhtml+xml"]),
+        Format::Json => ("application", &["application/json", "application/problem+json"]),
+    };
+    let mut explicit = None;
+    let mut wildcard: Option<f32> = None;
+    for item in accept.split(',') {
+        let mut parts = item.split(';');
+        let range = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let q = parts
+            .filter_map(|p| p.trim().strip_prefix("q=").or_else(|| p.trim().strip_prefix("Q=")))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0)
+            .clamp(0.0, 1.0);
+        if exact.contains(&range.as_str()) {
+            explicit = Some(explicit.map_or(q, |e: f32| e.max(q)));
+        } else if range == "*/*" || range.strip_suffix("/*") == Some(major) {
+            wildcard = Some(wildcard.map_or(q, |w| w.max(q)));
+        }
+    }
+    match (explicit, wildcard) {
+        (Some(q), _) => (q, q > 0.0),
+        (None, Some(q)) => (q, false),
+        (None, None) => (0.0, false),
+    }
+}
+
+fn fill(template: &str, status: &str, title: &str, detail: &str) -> String {
+    template
+        .replace("{{status}}", status)
+        .replace("{{title}}", title)
+        .replace("{{detail}}", detail)
+}
+
+fn builtin_html(status: &str, title: &str, detail: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{status} {title}</title>\n\
+         <style>body{{font-family:sans-serif;margin:3em auto;max-width:40em;color:#333}}</style>\n</head>\n\
+         <body>\n<h1>{status} {title}</h1>\n<p>{detail}</p>\n<hr>\n<p><small>{server}</small></p>\n</body>\n</html>\n",
+        server = crate::http::SERVER_NAME,
+    )
+}
+
+/// JSON 字符串内容的转义，不含两侧引号，用于填入文档中已有的字符串字面量
+fn json_escape(s: &str) -> String {
+    let quoted = serde_json::Value::from(s).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
@@ -0,0 +1,173 @@
+use crate::compression::Encoding;
+use crate::conditional::Validators;
+use crate::config::FileCacheConfig;
+use lru::LruCache;
+use serde::Serialize;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+/// 缓存在内存中的静态文件，连同验证器和按需生成的压缩版本
+///
+/// 不保存 MIME 类型：同一个预压缩文件可能代替不同的原文件发送
+#[derive(Debug)]
+pub struct CachedFile {
+    len: u64,
+    modified: Option<SystemTime>,
+    pub validators: Validators,
+    pub body: Arc<[u8]>,
+    /// 各编码的压缩结果，首次请求时生成
+    variants: Mutex<Vec<(Encoding, Arc<[u8]>)>>,
+}
+
+impl CachedFile {
+    pub fn new(metadata: &Metadata, validators: Validators, body: Vec<u8>) -> Self {
+        Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            validators,
+            body: body.into(),
+            variants: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 已生成的压缩版本
+    pub fn variant(&self, encoding: Encoding) -> Option<Arc<[u8]>> {
+        lock(&self.variants)
+            .iter()
+            .find(|(e, _)| *e == encoding)
+            .map(|(_, bytes)| Arc::clone(bytes))
+    }
+
+    /// 大小和修改时间都与磁盘上的文件一致时视为仍然有效
+    fn is_fresh(&self, metadata: &Metadata) -> bool {
+        self.len == metadata.len() && self.modified == metadata.modified().ok()
+    }
+
+    /// 占用的内存字节数，包括压缩版本
+    fn size(&self) -> u64 {
+        let variants: usize = lock(&self.variants).iter().map(|(_, bytes)| bytes.len()).sum();
+        (self.body.len() + variants) as u64
+    }
+}
+
+/// 缓存统计，供 `/api/metrics` 输出
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// 小文件的 LRU 缓存，按总字节数限制容量
+///
+/// 每次命中时比较文件的大小和修改时间，文件变化后旧内容立即失效。
+/// 只有不超过 `max_file_size` 的文件计入命中和未命中次数。
+pub struct FileCache {
+    config: FileCacheConfig,
+    state: Mutex<State>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+struct State {
+    entries: LruCache<PathBuf, Arc<CachedFile>>,
+    bytes: u64,
+}
+
+impl FileCache {
+    pub fn new(config: FileCacheConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State {
+                entries: LruCache::unbounded(),
+                bytes: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    /// 该长度的文件是否可以缓存
+    pub fn accepts(&self, len: u64) -> bool {
+        self.config.enabled && len <= self.config.max_file_size
+    }
+
+    /// 查找仍然有效的缓存，过期的条目随即移除
+    pub fn get(&self, path: &Path, metadata: &Metadata) -> Option<Arc<CachedFile>> {
+        if !self.accepts(metadata.len()) {
+            return None;
+        }
+        let mut state = lock(&self.state);
+        let found = match state.entries.get(path) {
+            Some(file) if file.is_fresh(metadata) => Some(Arc::clone(file)),
+            Some(_) => {
+                if let Some(stale) = state.entries.pop(path) {
+                    state.bytes -= stale.size();
+                }
+                None
+            }
+            None => None,
+        };
+        let counter = if found.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        found
+    }
+
+    /// 加入缓存，超出容量时淘汰最久未使用的条目
+    pub fn insert(&self, path: PathBuf, file: CachedFile) -> Arc<CachedFile> {
+        let file = Arc::new(file);
+        let mut state = lock(&self.state);
+        state.bytes += file.size();
+        if let Some(old) = state.entries.put(path, Arc::clone(&file)) {
+            state.bytes -= old.size();
+        }
+        self.evict(&mut state);
+        file
+    }
+
+    /// 保存压缩版本；条目已被淘汰时只返回结果，不再占用缓存容量
+    pub fn add_variant(&self, path: &Path, file: &Arc<CachedFile>, encoding: Encoding, bytes: Vec<u8>) -> Arc<[u8]> {
+        let bytes: Arc<[u8]> = bytes.into();
+        let mut state = lock(&self.state);
+        let cached = state.entries.peek(path).is_some_and(|f| Arc::ptr_eq(f, file));
+        if cached && file.variant(encoding).is_none() {
+            lock(&file.variants).push((encoding, Arc::clone(&bytes)));
+            state.bytes += bytes.len() as u64;
+            self.evict(&mut state);
+        }
+        bytes
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        let state = lock(&self.state);
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: state.entries.len(),
+            bytes: state.bytes,
+        }
+    }
+
+    fn evict(&self, state: &mut State) {
+        while state.bytes > self.config.max_size {
+            let Some((_, file)) = state.entries.pop_lru() else {
+                break;
+            };
+            state.bytes -= file.size();
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// 持锁期间不会 panic，中毒的锁仍可继续使用
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -1,81 +1,374 @@
-use std::{io::Result, path::Path};
-use tokio::{fs, net::TcpStream};
+use crate::compression::{self, Encoding};
+use crate::conditional::{self, Precondition, Validators};
+use crate::config::Config;
+use crate::file_cache::{CachedFile, FileCache};
+use crate::http::{Method, Request, Response, StatusCode};
+use crate::listing::url_encode;
+use crate::livereload::{self, LiveReload};
+use crate::middleware::{Compression, Cors};
+use crate::mime;
+use crate::range::{self, Ranges};
+use crate::router::{Context, Router, Routes};
+use crate::utils::resolve_safe_path;
+use std::io::{Cursor, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::fs;
 
-pub async fn handle_get_request(stream: &mut TcpStream, path: &str, log: &crate::utils::LogEntry) -> Result<()> {
-    let status = match serve_static_file(stream, path).await {
-        Ok(code) => code,
-        Err(_) => {
-            crate::utils::send_500_response(stream, b"500 Internal Server Error").await?;
-            "500".to_string()
-        }
+/// 可在配置文件 `routes` 中引用的内置 API 处理函数
+pub const API_HANDLERS: &[&str] = &["register", "login", "metrics"];
+
+/// 根据配置构建路由表：API 路由加上静态文件兜底，以及配置启用的中间件
+///
+/// 配置文件未声明 `routes` 时使用内置的 `/api` 路由。静态文件缓存随路由表一起创建，
+/// 重新加载配置后从空缓存开始。
+pub fn router(config: &Config) -> Router {
+    let cache = Arc::new(FileCache::new(config.file_cache.clone()));
+    let metrics = {
+        let cache = Arc::clone(&cache);
+        move |ctx: Context| handle_metrics(Arc::clone(&cache), ctx)
     };
-    log.log(&status);
-    Ok(())
+    let mut router = Router::new();
+    if config.routes.is_empty() {
+        router.group("/api", |api| {
+            api.post("/register", handle_register);
+            api.post("/login", handle_login);
+            api.get("/metrics", metrics.clone());
+        });
+    }
+    for route in &config.routes {
+        // 配置路由的第一级路径段保留给 API，例如 `/api/login` 保留 `/api`
+        if let Some(first) = route.path.split('/').nth(1)
+            && !first.starts_with([':', '*'])
+        {
+            router.reserve(&format!("/{}", first));
+        }
+        let methods = [Method::parse(&route.method).expect("validated in Config::validate")];
+        let registered = match route.handler.as_str() {
+            "register" => router.route(&methods, &route.path, handle_register),
+            "login" => router.route(&methods, &route.path, handle_login),
+            "metrics" => router.route(&methods, &route.path, metrics.clone()),
+            other => unreachable!("unknown handler `{}` passed validation", other),
+        };
+        registered.max_body_size(route.max_body_size);
+    }
+    if config.server.dev {
+        let live = Arc::new(LiveReload::start(&config.server.root));
+        router.get(livereload::EVENTS_PATH, move |ctx: Context| {
+            livereload::handle_events(Arc::clone(&live), ctx)
+        });
+        router.get(livereload::SCRIPT_PATH, livereload::handle_script);
+    }
+    router.fallback(move |ctx: Context| handle_get_request(Arc::clone(&cache), ctx));
+    if let Some(cors) = &config.cors {
+        router.layer(Cors::new(cors.clone()));
+    }
+    if config.compression.enabled {
+        router.layer(Compression::new(config.compression.clone()));
+    }
+    // 在压缩之内注入脚本，压缩的是注入后的页面；静态文件缓存在开发模式下因此不预先压缩
+    if config.server.dev {
+        router.layer(livereload::inject_script);
+    }
+    router
 }
 
-pub async fn handle_post_request(
-    stream: &mut TcpStream,
-    path: &str,
-    body: &str,
-    log: &crate::utils::LogEntry,
-) -> Result<()> {
-    match path {
-        "/api/register" => handle_register(stream, body, log).await,
-        "/api/login" => handle_login(stream, body, log).await,
-        _ => {
-            crate::utils::send_400_response(stream, b"400 Bad Request").await?;
-            log.log("404");
-            Ok(())
-        }
+pub async fn handle_get_request(cache: Arc<FileCache>, ctx: Context) -> Response {
+    match serve_static_file(&ctx.config, &cache, &ctx.request).await {
+        Ok(response) => response,
+        Err(_) => Response::error(StatusCode::InternalServerError, "500 Internal Server Error"),
     }
 }
 
-pub async fn serve_static_file(stream: &mut TcpStream, path: &str) -> Result<String> {
-    // 移除开头的斜杠，构建文件路径
-    let path = path.trim_start_matches('/');
-    let file_path = Path::new("public").join(path);
+pub async fn serve_static_file(config: &Config, cache: &FileCache, request: &Request) -> Result<Response> {
+    let path = request.path.as_str();
+    let mut file_url = path.to_string();
+    // 解析为根目录内的真实路径，拒绝目录穿越
+    let mut file_path = match resolve_safe_path(&config.server.root, path).await {
+        Ok(p) => p,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory) => {
+            // 单页应用的前端路由由入口页面处理，看起来像静态资源的路径仍然 404
+            match spa_entry(config, path).await {
+                Some((entry_url, entry)) => {
+                    file_url = entry_url;
+                    entry
+                }
+                None => return Ok(Response::error(StatusCode::NotFound, "File not found")),
+            }
+        }
+        Err(e) => {
+            return match e.kind() {
+                ErrorKind::NotFound | ErrorKind::NotADirectory => {
+                    Ok(Response::error(StatusCode::NotFound, "File not found"))
+                }
+                ErrorKind::PermissionDenied => Ok(Response::error(StatusCode::Forbidden, "Forbidden")),
+                ErrorKind::InvalidInput => Ok(Response::error(StatusCode::BadRequest, "Invalid path")),
+                _ => Err(e),
+            };
+        }
+    };
 
-    // 检查文件是否存在
-    if !file_path.exists() {
-        crate::utils::send_response(stream, "404 Not Found", b"File not found", "text/plain", None).await?;
-        return Ok("404".to_string());
+    if file_path.is_dir() {
+        // 目录必须以斜杠结尾，页面中的相对链接才能正确解析
+        if !path.ends_with('/') {
+            // 由解码后的路径重新编码，开头的多个斜杠合并为一个，
+            // 否则 `//example.com` 会被浏览器当作其他站点（开放重定向）
+            let segments: Vec<String> = path.trim_start_matches('/').split('/').map(url_encode).collect();
+            let query = request.target.split_once('?').map_or(String::new(), |(_, q)| format!("?{}", q));
+            return Ok(Response::new(StatusCode::MovedPermanently)
+                .header("Location", format!("/{}/{}", segments.join("/"), query)));
+        }
+        // index.html 同样可能是指向根目录之外的符号链接
+        match resolve_safe_path(&config.server.root, &format!("{}index.html", path)).await {
+            Ok(index) if index.is_file() => {
+                file_path = index;
+                file_url.push_str("index.html");
+            }
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                return Ok(Response::error(StatusCode::Forbidden, "Forbidden"));
+            }
+            _ if config.directory(path).is_some_and(|(_, d)| d.listing) => {
+                let accept = request.headers.get("accept");
+                return crate::listing::directory_listing(&file_path, path, &request.query, accept).await;
+            }
+            _ => return Ok(Response::error(StatusCode::Forbidden, "Directory listing is disabled")),
+        }
     }
 
-    // 读取文件内容
-    let contents = match fs::read(&file_path).await {
-        Ok(c) => c,
-        Err(_) => {
-            crate::utils::send_response(stream, "500 Internal Server Error", b"Error reading file", "text/plain", None).await?;
-            return Ok("500".to_string());
+    // 客户端接受时改发预压缩的同名文件，验证器和字节范围都针对实际发送的文件
+    // 开发模式下页面需要注入脚本，构建产物也可能已经过时，总是发送原文件
+    let (has_siblings, sibling) = if config.compression.precompressed && !config.server.dev {
+        precompressed_sibling(config, &file_url, request.headers.get("accept-encoding")).await
+    } else {
+        (false, None)
+    };
+    let extension = file_path.extension().and_then(|s| s.to_str()).map(str::to_ascii_lowercase);
+    let extension = extension.as_deref();
+    let (encoding, body_path) = match sibling {
+        Some((encoding, sibling)) => (Some(encoding), sibling),
+        // .svgz 本身就是 gzip 压缩的 SVG，以内容编码发送，浏览器才能直接显示
+        None if extension == Some("svgz") => (Some(Encoding::Gzip), file_path.clone()),
+        None => (None, file_path.clone()),
+    };
+
+    let read_error = || Response::error(StatusCode::InternalServerError, "Error reading file");
+    let Ok(metadata) = fs::metadata(&body_path).await else {
+        return Ok(read_error());
+    };
+    // 缓存命中时沿用已计算的验证器，不再读取文件
+    let cached = cache.get(&body_path, &metadata);
+    let validators = match &cached {
+        Some(file) => file.validators.clone(),
+        None => match Validators::for_file(&body_path, &metadata, config.server.etag).await {
+            Ok(validators) => validators,
+            Err(_) => return Ok(read_error()),
+        },
+    };
+    // MIME 类型按请求的原文件确定，即使发送的是预压缩文件
+    let mime_type = mime::for_file(&file_path, config).await;
+    let cache_control = match extension.and_then(|ext| config.cache.get(ext)) {
+        Some(policy) => policy.as_str(),
+        None => builtin_cache_control(extension),
+    };
+
+    // 304 与 200 携带相同的验证器和缓存策略
+    let status = match conditional::evaluate(request, &validators) {
+        Precondition::Proceed => StatusCode::Ok,
+        Precondition::NotModified => StatusCode::NotModified,
+        Precondition::Failed => return Ok(Response::error(StatusCode::PreconditionFailed, "Precondition Failed")),
+    };
+    let mut response = Response::new(status).header("Cache-Control", cache_control);
+    if let Some(etag) = &validators.etag {
+        response = response.header("ETag", etag.as_str());
+    }
+    if let Some(last_modified) = validators.last_modified_header() {
+        response = response.header("Last-Modified", last_modified);
+    }
+    if has_siblings {
+        response = response.header("Vary", "Accept-Encoding");
+    }
+    if status == StatusCode::NotModified {
+        return Ok(response);
+    }
+    if let Some(encoding) = encoding {
+        response = response.header("Content-Encoding", encoding.as_str());
+    }
+    response = response.header("Accept-Ranges", "bytes");
+
+    // 只有 GET 定义了范围请求；If-Range 不匹配时返回完整内容
+    let range = request.headers.get("range").filter(|_| request.method == Method::Get);
+    let if_range = request.headers.get("if-range");
+    if let Some(range) = range
+        && if_range.is_none_or(|v| range::if_range_matches(v, &validators))
+    {
+        let len = metadata.len();
+        match range::parse(range, len) {
+            Ranges::Full => {}
+            Ranges::Unsatisfiable => return Ok(range::unsatisfiable(len)),
+            Ranges::Partial(ranges) => {
+                let mut partial = range::partial(&body_path, &ranges, len, &mime_type).await?;
+                for (name, value) in response.headers.iter() {
+                    partial.headers.insert(name, value);
+                }
+                return Ok(partial);
+            }
         }
+    }
+
+    // 小文件读入缓存；读取期间文件发生变化时不缓存，按大文件处理
+    let cached = match cached {
+        Some(file) => Some(file),
+        None if cache.accepts(metadata.len()) => match fs::read(&body_path).await {
+            Ok(bytes) if bytes.len() as u64 == metadata.len() => {
+                let file = CachedFile::new(&metadata, validators, bytes);
+                Some(cache.insert(body_path.clone(), file))
+            }
+            _ => None,
+        },
+        None => None,
+    };
+    if let Some(file) = cached {
+        let response = response.header("Content-Type", mime_type);
+        if encoding.is_some() {
+            let len = file.body.len() as u64;
+            return Ok(response.stream(Cursor::new(Arc::clone(&file.body)), Some(len)));
+        }
+        return Ok(cached_response(config, cache, request, &body_path, &file, response).await);
+    }
+
+    // 以文件句柄作为消息体，写出时按块发送，不在内存中缓冲整个文件
+    let Ok(file) = fs::File::open(&body_path).await else {
+        return Ok(read_error());
     };
+    Ok(response.header("Content-Type", mime_type).file(file, metadata.len()))
+}
 
-    // 确定MIME类型
-    let mime_type = match file_path.extension().and_then(|s| s.to_str()) {
-        Some("html") => "text/html",
-        Some("css") => "text/css",
-        Some("js") => "application/javascript",
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("svg") => "image/svg+xml",
-        _ => "application/octet-stream",
+/// 从缓存发送文件；压缩中间件会压缩的响应改用缓存的压缩版本，只在首次请求时压缩
+async fn cached_response(
+    config: &Config,
+    cache: &FileCache,
+    request: &Request,
+    path: &Path,
+    file: &Arc<CachedFile>,
+    mut response: Response,
+) -> Response {
+    let compression = &config.compression;
+    let len = file.body.len() as u64;
+    // 开发模式下页面还要注入脚本，交给压缩中间件在注入之后压缩
+    let encoding = if compression.enabled
+        && !config.server.dev
+        && compression::is_transformable(&response, compression)
+    {
+        if !response.headers.has_token("Vary", "Accept-Encoding") {
+            response.headers.append("Vary", "Accept-Encoding");
+        }
+        compression::requested(&request.headers).filter(|_| len >= compression.min_size)
+    } else {
+        None
+    };
+    let Some(encoding) = encoding else {
+        return response.stream(Cursor::new(Arc::clone(&file.body)), Some(len));
     };
 
-    // 发送文件内容
-    crate::utils::send_response(stream, "200 OK", &contents, mime_type, None).await?;
-    Ok("200".to_string())
+    let encoded = match file.variant(encoding) {
+        Some(encoded) => encoded,
+        None => match compression::encode_bytes(file.body.to_vec(), encoding, compression).await {
+            Ok(encoded) => cache.add_variant(path, file, encoding, encoded),
+            Err(_) => return Response::error(StatusCode::InternalServerError, "500 Internal Server Error"),
+        },
+    };
+    if let Some(etag) = response.headers.get("ETag").map(|e| compression::etag_with_encoding(e, encoding)) {
+        response.headers.insert("ETag", etag);
+    }
+    response.headers.remove("Accept-Ranges");
+    let len = encoded.len() as u64;
+    response
+        .header("Content-Encoding", encoding.as_str())
+        .stream(Cursor::new(encoded), Some(len))
 }
 
-async fn handle_login(stream: &mut TcpStream, body: &str, log: &crate::utils::LogEntry)-> Result<()> {
-    Ok(())
+/// 单页应用模式下的入口页面，返回其 URL 路径和文件路径
+async fn spa_entry(config: &Config, path: &str) -> Option<(String, PathBuf)> {
+    let last_segment = path.rsplit('/').next().unwrap_or("");
+    if last_segment.contains('.') {
+        return None;
+    }
+    let (prefix, directory) = config.directory(path)?;
+    if !directory.spa {
+        return None;
+    }
+    let url = format!("{}/index.html", prefix.trim_end_matches('/'));
+    let entry = resolve_safe_path(&config.server.root, &url).await.ok()?;
+    entry.is_file().then_some((url, entry))
 }
-async fn handle_register(
-    stream: &mut TcpStream,
-    body: &str,
-    log: &crate::utils::LogEntry,
-) -> Result<()> {
-    Ok(())
+
+/// 查找 `.br`、`.gz` 预压缩文件并按 `Accept-Encoding` 选择
+///
+/// 返回是否存在任何预压缩版本（决定是否需要 `Vary`）以及选中的编码和文件路径。
+async fn precompressed_sibling(
+    config: &Config,
+    file_url: &str,
+    accept_encoding: Option<&str>,
+) -> (bool, Option<(Encoding, PathBuf)>) {
+    let mut siblings = Vec::new();
+    for (encoding, extension) in [(Encoding::Brotli, "br"), (Encoding::Gzip, "gz")] {
+        let url = format!("{}.{}", file_url, extension);
+        if let Ok(sibling) = resolve_safe_path(&config.server.root, &url).await
+            && sibling.is_file()
+        {
+            siblings.push((encoding, sibling));
+        }
+    }
+    let offered: Vec<Encoding> = siblings.iter().map(|(encoding, _)| *encoding).collect();
+    let chosen = compression::negotiate(accept_encoding, &offered);
+    let has_siblings = !siblings.is_empty();
+    (has_siblings, siblings.into_iter().find(|(encoding, _)| Some(*encoding) == chosen))
+}
+
+/// 未在配置中指定时的缓存策略：页面每次向服务器验证，静态资源缓存一小时
+fn builtin_cache_control(extension: Option<&str>) -> &'static str {
+    match extension {
+        Some("css") | Some("js") | Some("png") | Some("jpg") | Some("jpeg") | Some("gif") | Some("webp")
+        | Some("ico") | Some("svg") | Some("svgz") | Some("woff") | Some("woff2")
+        | Some("ttf") => "public, max-age=3600",
+        _ => "no-cache",
+    }
+}
+
+// 请求体先按原有规则校验，用户存储尚未接入，校验通过后返回 501
+async fn handle_login(ctx: Context) -> Response {
+    let Ok(data) = serde_json::from_slice::<serde_json::Value>(&ctx.request.body) else {
+        return Response::error(StatusCode::BadRequest, "Invalid JSON format");
+    };
+    let field = |name: &str| data.get(name).and_then(serde_json::Value::as_str).unwrap_or("").to_string();
+    if field("username").is_empty() || field("password").is_empty() {
+        return Response::error(StatusCode::BadRequest, "Username and password are required");
+    }
+    Response::error(StatusCode::NotImplemented, "Login is not available yet")
+}
+async fn handle_register(ctx: Context) -> Response {
+    let Ok(data) = serde_json::from_slice::<serde_json::Value>(&ctx.request.body) else {
+        return Response::error(StatusCode::BadRequest, "Invalid JSON format");
+    };
+    let field = |name: &str| data.get(name).and_then(serde_json::Value::as_str).unwrap_or("").to_string();
+    let (username, email, password) = (field("username"), field("email"), field("password"));
+    if username.is_empty() || email.is_empty() || password.is_empty() {
+        return Response::error(StatusCode::BadRequest, "Username, email and password are required");
+    }
+    if !email.contains('@') || !email.contains('.') {
+        return Response::error(StatusCode::BadRequest, "Invalid email format");
+    }
+    if password.len() < 6 {
+        return Response::error(StatusCode::BadRequest, "Password must be at least 6 characters");
+    }
+    Response::error(StatusCode::NotImplemented, "Registration is not available yet")
+}
+async fn handle_metrics(cache: Arc<FileCache>, _ctx: Context) -> Response {
+    let body = serde_json::json!({ "file_cache": cache.metrics() });
+    Response::new(StatusCode::Ok)
+        .header("Content-Type", "application/json")
+        .header("Cache-Control", "no-store")
+        .body(body.to_string())
 }
 /*
 async fn handle_register(
@@ -187,4 +480,132 @@ async fn handle_login(
     
     Ok(())
 }
-*/
\ No newline at end of file
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Body, Headers, RequestLine};
+    use crate::router::Params;
+    use async_compression::tokio::bufread::GzipDecoder;
+    use tokio::io::AsyncReadExt;
+
+    async fn get(router: &Arc<Router>, config: &Arc<Config>, path: &str, headers: &[(&str, &str)]) -> Response {
+        let mut request_headers = Headers::new();
+        for (name, value) in headers {
+            request_headers.append(name, value);
+        }
+        let line = RequestLine::parse(&format!("GET {} HTTP/1.1", path)).unwrap();
+        let ctx = Context {
+            request: line.into_request(request_headers, "127.0.0.1:1".parse().unwrap(), Vec::new()),
+            params: Params::default(),
+            config: Arc::clone(config),
+        };
+        Arc::clone(router).handle(ctx).await
+    }
+
+    async fn body(response: Response) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match response.body {
+            Body::Empty => {}
+            Body::Bytes(b) => bytes = b,
+            Body::File(file, len) => {
+                file.take(len).read_to_end(&mut bytes).await.unwrap();
+            }
+            Body::Stream(mut stream, _) => {
+                stream.read_to_end(&mut bytes).await.unwrap();
+            }
+        }
+        bytes
+    }
+
+    #[tokio::test]
+    async fn dev_mode_injects_script_into_compressed_cached_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        // 超过 compression.min_size，且小于 file_cache.max_file_size，会进入缓存
+        let page = format!("<html><body>{}</body></html>", "<p>live reload</p>".repeat(200));
+        assert!(page.len() >= 1024);
+        std::fs::write(dir.path().join("index.html"), &page).unwrap();
+        let mut config = Config::default();
+        config.server.root = dir.path().to_path_buf();
+        config.server.dev = true;
+        let router = Arc::new(router(&config));
+        let config = Arc::new(config);
+
+        // 第一次请求读入缓存，第二次从缓存发送，两次都须注入脚本
+        for _ in 0..2 {
+            let response = get(&router, &config, "/index.html", &[("accept-encoding", "gzip")]).await;
+            assert_eq!(response.status, StatusCode::Ok);
+            assert_eq!(response.headers.get("Content-Encoding"), Some("gzip"));
+            let encoded = body(response).await;
+            let mut decoded = String::new();
+            GzipDecoder::new(encoded.as_slice()).read_to_string(&mut decoded).await.unwrap();
+            assert!(decoded.contains("<script src=\"/__livereload.js\"></script>\n</body>"));
+        }
+
+        let response = get(&router, &config, "/index.html", &[]).await;
+        assert_eq!(response.headers.get("Content-Encoding"), None);
+        assert!(response.headers.get("ETag").is_some_and(|e| e.starts_with("W/")));
+        assert!(String::from_utf8(body(response).await).unwrap().contains("/__livereload.js"));
+    }
+
+    #[tokio::test]
+    async fn precompressed_sibling_keeps_the_requested_file_type() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.js"), "console.log(1);").unwrap();
+        std::fs::write(dir.path().join("app.js.gz"), b"\x1f\x8b not really gzip").unwrap();
+        let mut config = Config::default();
+        config.server.root = dir.path().to_path_buf();
+        let router = Arc::new(router(&config));
+        let config = Arc::new(config);
+
+        // 两个请求发送同一个文件，先请求的一方不能决定另一方的 MIME 类型
+        let response = get(&router, &config, "/app.js.gz", &[]).await;
+        assert_eq!(response.headers.get("Content-Type"), Some("application/gzip"));
+        assert_eq!(response.headers.get("Content-Encoding"), None);
+        let response = get(&router, &config, "/app.js", &[("accept-encoding", "gzip")]).await;
+        assert_eq!(response.headers.get("Content-Encoding"), Some("gzip"));
+        assert_eq!(
+            response.headers.get("Content-Type"),
+            Some("application/javascript; charset=utf-8")
+        );
+    }
+
+    #[tokio::test]
+    async fn directory_redirect_stays_on_this_site() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("app/a b")).unwrap();
+        let mut config = Config::default();
+        config.server.root = dir.path().to_path_buf();
+        let router = Arc::new(router(&config));
+        let config = Arc::new(config);
+
+        for (target, location) in [
+            ("/app", "/app/"),
+            ("//app", "/app/"),
+            ("///app?x=1", "/app/?x=1"),
+            ("/%2Fapp", "/app/"),
+            ("/app/a%20b", "/app/a%20b/"),
+        ] {
+            let response = get(&router, &config, target, &[]).await;
+            assert_eq!(response.status, StatusCode::MovedPermanently, "{}", target);
+            assert_eq!(response.headers.get("Location"), Some(location), "{}", target);
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_with_if_range_etag_uses_default_validators() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("data.bin"), b"0123456789").unwrap();
+        let mut config = Config::default();
+        config.server.root = dir.path().to_path_buf();
+        let router = Arc::new(router(&config));
+        let config = Arc::new(config);
+
+        let response = get(&router, &config, "/data.bin", &[]).await;
+        let etag = response.headers.get("ETag").unwrap().to_string();
+        assert!(!etag.starts_with("W/"));
+        let response = get(&router, &config, "/data.bin", &[("range", "bytes=4-"), ("if-range", &etag)]).await;
+        assert_eq!(response.status, StatusCode::PartialContent);
+        assert_eq!(body(response).await, b"456789");
+    }
+}
@@ -1,87 +1,543 @@
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+mod request;
+mod response;
+
+pub use request::{Headers, Method, Request, RequestLine, Version};
+pub use response::{Body, Response, SERVER_NAME, StatusCode};
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
-use std::collections::HashMap;
+use tokio_rustls::server::TlsStream;
+use std::io::ErrorKind;
 use std::net::SocketAddr;
+use crate::config::LimitsConfig;
+use crate::router::{Context, Params, Resolution};
+use crate::site::{Site, VirtualHosts};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{timeout, timeout_at};
+
+/// 承载 HTTP 报文的连接：明文 TCP 或 TLS
+pub trait Transport: AsyncRead + AsyncWrite + Unpin + Send {
+    /// 写出响应，`sent` 累计已写出的字节数
+    fn send_response(
+        &mut self,
+        response: Response,
+        version: Version,
+        sent: &mut u64,
+    ) -> impl Future<Output = std::io::Result<()>> + Send;
+}
+
+impl Transport for TcpStream {
+    // 明文连接可以用 sendfile 零拷贝发送文件
+    fn send_response(
+        &mut self,
+        response: Response,
+        version: Version,
+        sent: &mut u64,
+    ) -> impl Future<Output = std::io::Result<()>> + Send {
+        response.send_to(self, version, sent)
+    }
+}
+
+impl Transport for TlsStream<TcpStream> {
+    // 文件内容需要加密，只能经过用户态缓冲区逐块写出
+    fn send_response(
+        &mut self,
+        response: Response,
+        version: Version,
+        sent: &mut u64,
+    ) -> impl Future<Output = std::io::Result<()>> + Send {
+        response.write_to(self, version, sent)
+    }
+}
+
+pub async fn handle_connection<S: Transport>(stream: S, addr: SocketAddr, hosts: Arc<VirtualHosts>) -> std::io::Result<()> {
+    // 连接级别的设置取自默认站点，路由、根目录和错误页按每个请求的 Host 选择
+    let config = &hosts.default_site().config;
+    // 读取缓冲在整个连接中复用，流水线请求会留在缓冲区内按序处理
+    let mut reader = BufReader::new(stream);
+    let idle_timeout = Duration::from_secs(config.limits.keep_alive_timeout);
+    let request_timeout = Duration::from_secs(config.limits.request_timeout);
+    let mut served = 0;
+
+    loop {
+        // 解析请求行，空闲超时或对端关闭时结束连接
+        let request_line = match timeout(idle_timeout, parse_request_line(&mut reader, &config.limits)).await {
+            Ok(Ok(Some(result))) => result,
+            Ok(Ok(None)) | Err(_) => return Ok(()),
+            Ok(Err(RequestError::Io(e))) => return Err(e),
+            Ok(Err(e)) => {
+                let log = crate::utils::LogEntry::new("UNKNOWN".to_string(), "INVALID".to_string(), Some(addr));
+                send(reader.get_mut(), e.into_response(), Version::Http11, false, &log).await?;
+                return Ok(());
+            }
+        };
+        // 请求行之后的请求头和请求体须在限定时间内读完，防止慢速客户端长期占用连接
+        let deadline = tokio::time::Instant::now() + request_timeout;
+        let mut headers = match timeout_at(deadline, parse_headers(&mut reader, &config.limits)).await {
+            Ok(Ok(headers)) => headers,
+            Ok(Err(RequestError::Io(e))) => return Err(e),
+            Ok(Err(e)) => {
+                let response = e.into_response();
+                return reject(&mut reader, hosts.default_site(), &request_line, &Headers::new(), addr, response).await;
+            }
+            Err(_) => {
+                let response = RequestError::Timeout.into_response();
+                return reject(&mut reader, hosts.default_site(), &request_line, &Headers::new(), addr, response).await;
+            }
+        };
+        // HTTP/1.1 请求必须恰好带一个有效的 Host（RFC 9112 §3.2），请求体无法确定边界，只能关闭连接
+        let host = match headers.get("host").map(crate::site::host_name) {
+            Some(Some(host)) => Some(host),
+            None if request_line.version == Version::Http10 => None,
+            _ => {
+                let response = Response::error(StatusCode::BadRequest, "Missing or invalid Host header");
+                return reject(&mut reader, hosts.default_site(), &request_line, &headers, addr, response).await;
+            }
+        };
+        let site = Arc::clone(hosts.select(host.as_deref()));
+        // 读取请求体，报文分帧有误或超出上限时无法确定下一个请求的起点，只能关闭连接；
+        // 请求体上限按原始请求匹配的路由确定，中间件改写请求不影响已读取的请求体
+        let max_body_size = match site.router.resolve(&request_line.method, &request_line.path) {
+            Resolution::Matched { max_body_size: Some(limit), .. } => limit,
+            _ => site.config.limits.max_body_size,
+        };
+        let body = match timeout_at(deadline, read_body(&mut reader, &mut headers, &config.limits, max_body_size)).await {
+            Ok(Ok(body)) => body,
+            Ok(Err(RequestError::Io(e))) => return Err(e),
+            Ok(Err(e)) => return reject(&mut reader, &site, &request_line, &headers, addr, e.into_response()).await,
+            Err(_) => {
+                let response = RequestError::Timeout.into_response();
+                return reject(&mut reader, &site, &request_line, &headers, addr, response).await;
+            }
+        };
+
+        let request = request_line.into_request(headers, addr, body);
+        served += 1;
+
+        // 创建日志条目并路由请求
+        let log = crate::utils::LogEntry::new(request.method.to_string(), request.target.clone(), Some(request.peer))
+            .with_file(&site.config.log.file);
+        let version = request.version;
+        let keep_alive = wants_keep_alive(&request) && served < config.limits.max_requests;
+        let mut response = route_request(&site, request).await;
+
+        let keep_alive = keep_alive
+            && !response.headers.has_token("Connection", "close")
+            && !response.requires_close(version);
+        if keep_alive && version == Version::Http10 {
+            response.headers.insert(
+                "Keep-Alive",
+                format!(
+                    "timeout={}, max={}",
+                    config.limits.keep_alive_timeout,
+                    config.limits.max_requests - served
+                ),
+            );
+        }
+        send(reader.get_mut(), response, version, keep_alive, &log).await?;
+
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+/// 补全 `Connection` 头后写出响应，并记录访问日志
+async fn send<S: Transport>(
+    stream: &mut S,
+    mut response: Response,
+    version: Version,
+    keep_alive: bool,
+    log: &crate::utils::LogEntry,
+) -> std::io::Result<()> {
+    match (keep_alive, version) {
+        (false, _) => response.headers.insert("Connection", "close"),
+        (true, Version::Http10) => response.headers.insert("Connection", "keep-alive"),
+        (true, Version::Http11) => response.headers.remove("Connection"),
+    }
+    let status = response.status.as_u16().to_string();
+    // 写出中途失败（如客户端断开）时也记录已发送的字节数
+    let mut sent = 0;
+    let result = stream.send_response(response, version, &mut sent).await;
+    log.log(&status, sent);
+    result
+}
+
+/// 拒绝请求后继续丢弃客户端数据的最长时间和最大字节数
+const LINGER_TIMEOUT: Duration = Duration::from_secs(2);
+const LINGER_LIMIT: u64 = 1024 * 1024;
+
+/// 读取请求失败的原因
+#[derive(Debug)]
+enum RequestError {
+    /// 报文格式错误，或分帧有歧义
+    Invalid(&'static str),
+    /// 请求行超过长度上限
+    UriTooLong,
+    /// 语法正确但不是 HTTP/1.0 或 HTTP/1.1
+    VersionNotSupported,
+    /// 请求头行过长或字段过多
+    HeadersTooLarge,
+    /// 不支持的传输编码
+    Unsupported(String),
+    /// 请求体超出路由允许的大小
+    TooLarge,
+    /// 未能在 `limits.request_timeout` 内读完请求
+    Timeout,
+    Io(std::io::Error),
+}
+
+impl From<std::io::Error> for RequestError {
+    fn from(e: std::io::Error) -> Self {
+        RequestError::Io(e)
+    }
+}
+
+impl RequestError {
+    fn into_response(self) -> Response {
+        match self {
+            RequestError::Invalid(message) => Response::error(StatusCode::BadRequest, message),
+            RequestError::UriTooLong => Response::error(StatusCode::UriTooLong, "URI Too Long"),
+            RequestError::VersionNotSupported => {
+                Response::error(StatusCode::HttpVersionNotSupported, "HTTP Version Not Supported")
+            }
+            RequestError::HeadersTooLarge => {
+                Response::error(StatusCode::RequestHeaderFieldsTooLarge, "Request Header Fields Too Large")
+            }
+            RequestError::Unsupported(encoding) => Response::error(
+                StatusCode::NotImplemented,
+                &format!("Transfer coding not implemented: {}", encoding),
+            ),
+            RequestError::TooLarge => Response::error(StatusCode::ContentTooLarge, "Content Too Large"),
+            RequestError::Timeout => Response::error(StatusCode::RequestTimeout, "Request Timeout"),
+            RequestError::Io(e) => Response::error(StatusCode::BadRequest, &e.to_string()),
+        }
+    }
+}
+
+/// 拒绝无法继续处理的请求：按站点配置渲染错误页，发送后关闭连接
+async fn reject<S: Transport>(
+    reader: &mut BufReader<S>,
+    site: &Site,
+    request_line: &RequestLine,
+    headers: &Headers,
+    addr: SocketAddr,
+    response: Response,
+) -> std::io::Result<()> {
+    let log = crate::utils::LogEntry::new(request_line.method.to_string(), request_line.target.clone(), Some(addr))
+        .with_file(&site.config.log.file);
+    let response = site.errors.render(response, headers.get("accept"), &request_line.path);
+    send(reader.get_mut(), response, request_line.version, false, &log).await?;
+    linger(reader).await;
+    Ok(())
+}
+
+/// 根据协议版本和 `Connection` 头判断客户端是否希望保持连接
+fn wants_keep_alive(request: &Request) -> bool {
+    match request.version {
+        Version::Http11 => !request.headers.has_token("connection", "close"),
+        Version::Http10 => request.headers.has_token("connection", "keep-alive"),
+    }
+}
+
+/// 读取一行到 `line`，包括行尾的换行符，返回读取的字节数；连接关闭时返回 0
+///
+/// 超过 `limit` 字节仍未遇到换行时停止缓冲，返回 `too_long`。
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+    limit: usize,
+    too_long: RequestError,
+) -> Result<usize, RequestError> {
+    let start = line.len();
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(line.len() - start);
+        }
+        let (chunk, complete) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (&available[..=i], true),
+            None => (available, false),
+        };
+        if line.len() - start + chunk.len() > limit {
+            return Err(too_long);
+        }
+        line.extend_from_slice(chunk);
+        let n = chunk.len();
+        reader.consume(n);
+        if complete {
+            return Ok(line.len() - start);
+        }
+    }
+}
+
+/// 读取并解析请求行；连接在请求之间关闭时返回 `None`
+async fn parse_request_line<S: Transport>(
+    reader: &mut BufReader<S>,
+    limits: &LimitsConfig,
+) -> Result<Option<RequestLine>, RequestError> {
+    let mut line = Vec::new();
+    // 忽略请求之间多余的空行
+    while line.trim_ascii().is_empty() {
+        line.clear();
+        if read_line(reader, &mut line, limits.max_line_size, RequestError::UriTooLong).await? == 0 {
+            return Ok(None);
+        }
+    }
+
+    let line = std::str::from_utf8(&line).map_err(|_| RequestError::Invalid("Invalid request"))?;
+    match RequestLine::parse(line) {
+        Some(request_line) => Ok(Some(request_line)),
+        // 形如 `HTTP/2.0` 的版本号格式正确，只是不受支持
+        None if line.split_whitespace().nth(2).is_some_and(|v| {
+            let v = v.as_bytes();
+            v.len() == 8 && v.starts_with(b"HTTP/") && v[5].is_ascii_digit() && v[6] == b'.' && v[7].is_ascii_digit()
+        }) =>
+        {
+            Err(RequestError::VersionNotSupported)
+        }
+        None => Err(RequestError::Invalid("Invalid request")),
+    }
+}
+/// 读取请求头直到空行；单行超过 `limits.max_line_size` 或字段数超过 `limits.max_headers` 时拒绝
+async fn parse_headers<R: AsyncBufRead + Unpin>(reader: &mut R, limits: &LimitsConfig) -> Result<Headers, RequestError> {
+    let mut headers = Headers::new();
+    let mut line = Vec::new();
+    let mut count = 0;
 
-pub async fn handle_connection(mut stream: TcpStream, addr: SocketAddr) -> std::io::Result<()> {
-    let mut reader = BufReader::new(&mut stream);
-    // 解析请求行
-    let (method, path) = match parse_request_line(&mut reader).await {
-        Ok(result) => result,
-        Err(_) => return handle_invalid_request(&mut stream, addr).await,
-    };
-    // 解析请求头
-    let headers = parse_headers(&mut reader).await?;
-    // 读取请求体
-    let content_length = headers
-        .get("content-length")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(0);
-    let body = read_body(&mut reader, content_length).await?;
-    // 创建日志条目并路由请求
-    let log = crate::utils::LogEntry::new(method.clone(), path.clone(), Some(addr));
-    route_request(&mut stream, &method, &path, &body, &log).await
-}
-async fn parse_request_line(reader: &mut BufReader<&mut TcpStream>) -> std::io::Result<(String, String)> {
-    let mut request_line = String::new();
-    reader.read_line(&mut request_line).await?;
-    
-    let parts: Vec<&str> = request_line.trim().split_whitespace().collect();
-    if parts.len() != 3 {
-        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid request line"));
-    }
-    
-    Ok((parts[0].to_string(), parts[1].to_string()))
-}
-async fn parse_headers(reader: &mut BufReader<&mut TcpStream>) -> std::io::Result<HashMap<String, String>> {
-    let mut headers = HashMap::new();
-    let mut line = String::new();
-    
     loop {
         line.clear();
-        let bytes_read = reader.read_line(&mut line).await?;
-        if bytes_read == 0 || matches!(line.as_str(), "\r\n" | "\n") {
+        let bytes_read = read_line(reader, &mut line, limits.max_line_size, RequestError::HeadersTooLarge).await?;
+        // 空行之前连接就已关闭，说明请求不完整，不能当作完整的请求处理
+        if bytes_read == 0 || !line.ends_with(b"\n") {
+            return Err(RequestError::Io(ErrorKind::UnexpectedEof.into()));
+        }
+        if matches!(line.as_slice(), b"\r\n" | b"\n") {
             break;
         }
-        
-        if let Some((key, value)) = line.trim_end_matches(&['\r', '\n']).split_once(':') {
-            headers.insert(key.trim().to_lowercase(), value.trim().to_string());
+        count += 1;
+        if count > limits.max_headers {
+            return Err(RequestError::HeadersTooLarge);
+        }
+
+        let line = std::str::from_utf8(&line).map_err(|_| RequestError::Invalid("Invalid header encoding"))?;
+        let Some((name, value)) = line.trim_end_matches(['\r', '\n']).split_once(':') else {
+            return Err(RequestError::Invalid("Invalid header line"));
+        };
+        // 字段名与冒号之间不允许空白，折叠行同样拒绝，否则代理与本服务器可能对同一报文解析出不同的请求头
+        if !request::is_token(name) {
+            return Err(RequestError::Invalid("Invalid header field name"));
         }
+        headers.append(name, value.trim());
     }
-    
+
     Ok(headers)
 }
-async fn read_body(reader: &mut BufReader<&mut TcpStream>, content_length: usize) -> std::io::Result<String> {
-    const MAX_BODY_SIZE: usize = 1024 * 1024;
-    let body_size = content_length.min(MAX_BODY_SIZE);
-    
-    if body_size == 0 {
-        return Ok(String::new());
-    }
-    
-    let mut body = vec![0; body_size];
-    reader.read_exact(&mut body).await?;
-    Ok(String::from_utf8_lossy(&body).into_owned())
-}
-async fn handle_invalid_request(stream: &mut TcpStream, addr: SocketAddr) -> std::io::Result<()> {
-    let log = crate::utils::LogEntry::new("UNKNOWN".to_string(), "INVALID".to_string(), Some(addr));
-    log.log("400");
-    crate::utils::send_400_response(stream, b"Invalid request").await
-}
-async fn route_request(
-    stream: &mut TcpStream,
-    method: &str,
-    path: &str,
-    body: &str,
-    log: &crate::utils::LogEntry,
-) -> std::io::Result<()> {
-    match method {
-        "GET" => crate::handlers::handle_get_request(stream, path, log).await,
-        "POST" => crate::handlers::handle_post_request(stream, path, body, log).await,
-        _ => {
-            crate::utils::send_405_response(stream, b"Method Not Allowed").await?;
-            log.log("405");
-            Ok(())
+/// 读取请求体，支持 `Content-Length` 与 `Transfer-Encoding: chunked` 两种分帧方式
+///
+/// 声明长度超过 `max_body_size` 时在读取前即返回 [`RequestError::TooLarge`]。
+async fn read_body<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    headers: &mut Headers,
+    limits: &LimitsConfig,
+    max_body_size: usize,
+) -> Result<Vec<u8>, RequestError> {
+    match headers.get("transfer-encoding") {
+        // 同时出现两种分帧方式可能导致请求走私（RFC 9112 §6.3）
+        Some(_) if headers.contains("content-length") => {
+            Err(RequestError::Invalid("Content-Length and Transfer-Encoding are both present"))
+        }
+        Some(encoding) => {
+            let codings: Vec<&str> = encoding.split(',').map(str::trim).collect();
+            if !codings.last().is_some_and(|c| c.eq_ignore_ascii_case("chunked")) {
+                return Err(RequestError::Invalid("chunked must be the final transfer coding"));
+            }
+            if codings.len() > 1 {
+                return Err(RequestError::Unsupported(encoding.to_string()));
+            }
+            read_chunked_body(reader, headers, limits, max_body_size).await
+        }
+        None => {
+            // 只接受 1*DIGIT，`parse` 允许的 `+` 号会让前后端对长度的理解不一致
+            let content_length = match headers.get("content-length") {
+                Some(value) if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => value
+                    .parse::<usize>()
+                    .map_err(|_| RequestError::Invalid("invalid Content-Length"))?,
+                Some(_) => return Err(RequestError::Invalid("invalid Content-Length")),
+                None => 0,
+            };
+            if content_length > max_body_size {
+                return Err(RequestError::TooLarge);
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).await?;
+            Ok(body)
         }
     }
-}
\ No newline at end of file
+}
+/// 解码分块传输的请求体，忽略分块扩展和尾部字段
+async fn read_chunked_body<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    headers: &mut Headers,
+    limits: &LimitsConfig,
+    max_body_size: usize,
+) -> Result<Vec<u8>, RequestError> {
+    let mut body = Vec::new();
+    let mut line = Vec::new();
+    let invalid_size = || RequestError::Invalid("invalid chunk size");
+
+    loop {
+        // chunk-size [ chunk-ext ] CRLF
+        line.clear();
+        if read_line(reader, &mut line, limits.max_line_size, invalid_size()).await? == 0 {
+            return Err(RequestError::Io(ErrorKind::UnexpectedEof.into()));
+        }
+        let line = std::str::from_utf8(&line).map_err(|_| invalid_size())?;
+        // chunk-size 为 1*HEXDIG，分号前允许空白
+        let size_field = line.split(';').next().unwrap_or("").trim_end();
+        if size_field.is_empty() || !size_field.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid_size());
+        }
+        let size = usize::from_str_radix(size_field, 16).map_err(|_| invalid_size())?;
+        if size == 0 {
+            break;
+        }
+        if body.len().saturating_add(size) > max_body_size {
+            return Err(RequestError::TooLarge);
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..]).await?;
+
+        // 每个分块数据后必须紧跟 CRLF
+        let mut crlf = Vec::new();
+        read_line(reader, &mut crlf, 2, RequestError::Invalid("missing CRLF after chunk data")).await?;
+        if !matches!(crlf.as_slice(), b"\r\n" | b"\n") {
+            return Err(RequestError::Invalid("missing CRLF after chunk data"));
+        }
+    }
+
+    // 尾部字段不能当作请求头使用（RFC 9110 §6.5.1），读出后直接丢弃
+    parse_headers(reader, limits).await?;
+    headers.remove("transfer-encoding");
+    Ok(body)
+}
+/// 半关闭连接后在限定时间内丢弃客户端仍在发送的数据，
+/// 避免未读数据触发 RST 导致客户端收不到错误响应
+async fn linger<S: Transport>(reader: &mut BufReader<S>) {
+    if reader.get_mut().shutdown().await.is_err() {
+        return;
+    }
+    let mut remaining = reader.take(LINGER_LIMIT);
+    let _ = timeout(LINGER_TIMEOUT, tokio::io::copy(&mut remaining, &mut tokio::io::sink())).await;
+}
+async fn route_request(site: &Site, request: Request) -> Response {
+    let head = request.method == Method::Head;
+    let accept = request.headers.get("accept").map(str::to_string);
+    let path = request.path.clone();
+    let ctx = Context {
+        request,
+        params: Params::default(),
+        config: Arc::clone(&site.config),
+    };
+    let response = Arc::clone(&site.router).handle(ctx).await;
+    let response = site.errors.render(response, accept.as_deref(), &path);
+    // HEAD 与 GET 走同一条处理路径，只是不发送消息体
+    if head {
+        response.without_body()
+    } else {
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body(raw: &str, headers: &[(&str, &str)]) -> (Result<Vec<u8>, RequestError>, Headers) {
+        let mut request_headers = Headers::new();
+        for (name, value) in headers {
+            request_headers.append(name, value);
+        }
+        let result = read_body(&mut raw.as_bytes(), &mut request_headers, &LimitsConfig::default(), 1024).await;
+        (result, request_headers)
+    }
+
+    #[tokio::test]
+    async fn chunked_trailers_are_not_merged_into_headers() {
+        let raw = "5\r\nhello\r\n0\r\nAuthorization: Bearer forged\r\nX-Checksum: 1\r\n\r\n";
+        let (result, headers) = body(raw, &[("transfer-encoding", "chunked")]).await;
+        assert_eq!(result.unwrap(), b"hello");
+        assert!(!headers.contains("authorization"));
+        assert!(!headers.contains("x-checksum"));
+        assert!(!headers.contains("transfer-encoding"));
+    }
+
+    #[tokio::test]
+    async fn headers_cut_off_before_the_blank_line_are_rejected() {
+        let limits = LimitsConfig::default();
+        for raw in ["", "Host: example.com\r\n", "Host: example.com\r\nContent-Len"] {
+            match parse_headers(&mut raw.as_bytes(), &limits).await {
+                Err(RequestError::Io(e)) => assert_eq!(e.kind(), ErrorKind::UnexpectedEof, "{:?}", raw),
+                other => panic!("{:?} parsed as {:?}", raw, other.map(|_| ())),
+            }
+        }
+        let headers = parse_headers(&mut "Host: example.com\r\n\r\n".as_bytes(), &limits).await;
+        assert_eq!(headers.unwrap().get("host"), Some("example.com"));
+    }
+
+    #[tokio::test]
+    async fn reads_content_length_and_chunked_bodies() {
+        let (result, _) = body("hello, world", &[("content-length", "5")]).await;
+        assert_eq!(result.unwrap(), b"hello");
+        let (result, _) = body("", &[]).await;
+        assert!(result.unwrap().is_empty());
+        // 分块扩展被忽略，分块大小不区分大小写
+        let raw = "5;name=value\r\nhello\r\nA ; ext\r\n, world!!!\r\n0\r\n\r\n";
+        let (result, _) = body(raw, &[("transfer-encoding", "chunked")]).await;
+        assert_eq!(result.unwrap(), b"hello, world!!!");
+    }
+
+    #[tokio::test]
+    async fn rejects_ambiguous_framing() {
+        let framing = [("content-length", "5"), ("transfer-encoding", "chunked")];
+        let (result, _) = body("5\r\nhello\r\n0\r\n\r\n", &framing).await;
+        assert!(matches!(result, Err(RequestError::Invalid(_))));
+        for length in ["+5", "-5", "5, 5", " ", "0x5"] {
+            let (result, _) = body("hello", &[("content-length", length)]).await;
+            assert!(matches!(result, Err(RequestError::Invalid(_))), "{:?}", length);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_malformed_chunks() {
+        for raw in [
+            "zz\r\nhello\r\n0\r\n\r\n",
+            "+5\r\nhello\r\n0\r\n\r\n",
+            "-5\r\nhello\r\n0\r\n\r\n",
+            "0x5\r\nhello\r\n0\r\n\r\n",
+            "\r\nhello\r\n0\r\n\r\n",
+            // 分块数据后缺少 CRLF
+            "5\r\nhelloX\r\n0\r\n\r\n",
+            "5\r\nhello0\r\n\r\n",
+        ] {
+            let (result, _) = body(raw, &[("transfer-encoding", "chunked")]).await;
+            assert!(matches!(result, Err(RequestError::Invalid(_))), "{:?}", raw);
+        }
+        let (result, _) = body("5\r\nhel", &[("transfer-encoding", "chunked")]).await;
+        assert!(matches!(result, Err(RequestError::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn enforces_the_body_size_limit() {
+        let (result, _) = body("", &[("content-length", "1025")]).await;
+        assert!(matches!(result, Err(RequestError::TooLarge)));
+        // 每个分块都不超过上限，总和超过
+        let chunk = "x".repeat(0x300);
+        let raw = format!("300\r\n{}\r\n300\r\n{}\r\n0\r\n\r\n", chunk, chunk);
+        let (result, _) = body(&raw, &[("transfer-encoding", "chunked")]).await;
+        assert!(matches!(result, Err(RequestError::TooLarge)));
+    }
+}
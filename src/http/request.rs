@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+
+/// HTTP 请求方法
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Patch,
+    Options,
+    Trace,
+    Connect,
+    /// 其它扩展方法，保留原始大小写
+    Other(String),
+}
+
+impl Method {
+    /// 解析请求行中的方法，方法名区分大小写
+    pub fn parse(s: &str) -> Option<Self> {
+        if !is_token(s) {
+            return None;
+        }
+        Some(match s {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "PATCH" => Method::Patch,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "CONNECT" => Method::Connect,
+            other => Method::Other(other.to_string()),
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Patch => "PATCH",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Connect => "CONNECT",
+            Method::Other(s) => s,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// HTTP 协议版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    Http10,
+    Http11,
+}
+
+impl Version {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "HTTP/1.0" => Some(Version::Http10),
+            "HTTP/1.1" => Some(Version::Http11),
+            _ => None,
+        }
+    }
+}
+
+/// 请求头集合，字段名不区分大小写
+///
+/// 重复出现的字段按 RFC 9110 §5.3 以 `, ` 合并为一个值。
+#[derive(Debug, Clone, Default)]
+pub struct Headers {
+    map: HashMap<String, String>,
+}
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.map.get(&name.to_ascii_lowercase()).map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.map.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// 追加字段，已存在时与原值合并
+    pub fn append(&mut self, name: &str, value: &str) {
+        self.map
+            .entry(name.to_ascii_lowercase())
+            .and_modify(|v| {
+                v.push_str(", ");
+                v.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        self.map.remove(&name.to_ascii_lowercase())
+    }
+
+    /// 判断逗号分隔的字段值中是否包含指定标记（不区分大小写）
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.get(name)
+            .is_some_and(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    }
+}
+
+/// 解析后的 HTTP 请求
+///
+/// 查询参数和请求体留给各 API 处理函数按需读取。
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: Method,
+    /// 请求行中的原始请求目标，包含查询字符串
+    pub target: String,
+    /// 百分号解码后的路径
+    pub path: String,
+    /// 按出现顺序保存的查询参数
+    pub query: Vec<(String, String)>,
+    pub version: Version,
+    pub headers: Headers,
+    pub peer: SocketAddr,
+    pub body: Vec<u8>,
+}
+
+/// 请求行解析结果
+#[derive(Debug)]
+pub struct RequestLine {
+    pub method: Method,
+    pub target: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub version: Version,
+}
+
+impl RequestLine {
+    /// 解析形如 `GET /index.html?a=1 HTTP/1.1` 的请求行
+    pub fn parse(line: &str) -> Option<Self> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let [method, target, version] = parts[..] else {
+            return None;
+        };
+        let method = Method::parse(method)?;
+        let version = Version::parse(version)?;
+        if !(target.starts_with('/') || method == Method::Options && target == "*") {
+            return None;
+        }
+
+        let (raw_path, raw_query) = match target.split_once('?') {
+            Some((path, query)) => (path, query),
+            None => (target, ""),
+        };
+        let path = String::from_utf8(percent_decode(raw_path.as_bytes(), false)?).ok()?;
+        let query = parse_query(raw_query);
+
+        Some(Self {
+            method,
+            target: target.to_string(),
+            path,
+            query,
+            version,
+        })
+    }
+
+    pub fn into_request(self, headers: Headers, peer: SocketAddr, body: Vec<u8>) -> Request {
+        Request {
+            method: self.method,
+            target: self.target,
+            path: self.path,
+            query: self.query,
+            version: self.version,
+            headers,
+            peer,
+            body,
+        }
+    }
+}
+
+/// 解析 `application/x-www-form-urlencoded` 格式的查询字符串
+///
+/// 查询参数按宽松方式解码：非法转义原样保留，非 UTF-8 字节替换为 U+FFFD。
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    let decode = |s: &str| match percent_decode(s.as_bytes(), true) {
+        Some(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        None => s.to_string(),
+    };
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+/// 百分号解码，`plus_as_space` 为真时把 `+` 视为空格；遇到非法转义返回 `None`
+pub fn percent_decode(input: &[u8], plus_as_space: bool) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'%' => {
+                let hex = input.get(i + 1..i + 3)?;
+                if !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return None;
+                }
+                output.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                output.push(b' ');
+                i += 1;
+            }
+            b => {
+                output.push(b);
+                i += 1;
+            }
+        }
+    }
+    Some(output)
+}
+
+/// 方法名和字段名的语法：由 token 字符组成的非空字符串（RFC 9110 §5.6.2）
+pub fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(is_token_byte)
+}
+
+/// RFC 9110 §5.6.2 中 token 允许的字符
+fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
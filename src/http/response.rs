@@ -0,0 +1,458 @@
+use super::Version;
+use chrono::Utc;
+use std::fmt;
+use std::pin::Pin;
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// `Server` 响应头的取值
+pub const SERVER_NAME: &str = concat!("web_server_rust/", env!("CARGO_PKG_VERSION"));
+
+/// HTTP 状态码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusCode {
+    Ok,
+    NoContent,
+    PartialContent,
+    MovedPermanently,
+    NotModified,
+    BadRequest,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    RequestTimeout,
+    PreconditionFailed,
+    ContentTooLarge,
+    UriTooLong,
+    RangeNotSatisfiable,
+    RequestHeaderFieldsTooLarge,
+    InternalServerError,
+    NotImplemented,
+    HttpVersionNotSupported,
+}
+
+impl StatusCode {
+    pub fn as_u16(&self) -> u16 {
+        match self {
+            StatusCode::Ok => 200,
+            StatusCode::NoContent => 204,
+            StatusCode::PartialContent => 206,
+            StatusCode::MovedPermanently => 301,
+            StatusCode::NotModified => 304,
+            StatusCode::BadRequest => 400,
+            StatusCode::Forbidden => 403,
+            StatusCode::NotFound => 404,
+            StatusCode::MethodNotAllowed => 405,
+            StatusCode::RequestTimeout => 408,
+            StatusCode::PreconditionFailed => 412,
+            StatusCode::ContentTooLarge => 413,
+            StatusCode::UriTooLong => 414,
+            StatusCode::RangeNotSatisfiable => 416,
+            StatusCode::RequestHeaderFieldsTooLarge => 431,
+            StatusCode::InternalServerError => 500,
+            StatusCode::NotImplemented => 501,
+            StatusCode::HttpVersionNotSupported => 505,
+        }
+    }
+
+    pub fn reason_phrase(&self) -> &'static str {
+        match self {
+            StatusCode::Ok => "OK",
+            StatusCode::NoContent => "No Content",
+            StatusCode::PartialContent => "Partial Content",
+            StatusCode::MovedPermanently => "Moved Permanently",
+            StatusCode::NotModified => "Not Modified",
+            StatusCode::BadRequest => "Bad Request",
+            StatusCode::Forbidden => "Forbidden",
+            StatusCode::NotFound => "Not Found",
+            StatusCode::MethodNotAllowed => "Method Not Allowed",
+            StatusCode::RequestTimeout => "Request Timeout",
+            StatusCode::PreconditionFailed => "Precondition Failed",
+            StatusCode::ContentTooLarge => "Content Too Large",
+            StatusCode::UriTooLong => "URI Too Long",
+            StatusCode::RangeNotSatisfiable => "Range Not Satisfiable",
+            StatusCode::RequestHeaderFieldsTooLarge => "Request Header Fields Too Large",
+            StatusCode::InternalServerError => "Internal Server Error",
+            StatusCode::NotImplemented => "Not Implemented",
+            StatusCode::HttpVersionNotSupported => "HTTP Version Not Supported",
+        }
+    }
+
+    /// 1xx、204 和 304 响应不能携带消息体
+    pub fn allows_body(&self) -> bool {
+        !matches!(self, StatusCode::NoContent | StatusCode::NotModified)
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.as_u16(), self.reason_phrase())
+    }
+}
+
+/// 响应头集合，允许同名字段出现多次（如 `Set-Cookie`），按插入顺序输出
+#[derive(Debug, Clone, Default)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    /// 设置字段，替换所有同名旧值
+    pub fn insert(&mut self, name: &str, value: impl Into<String>) {
+        self.remove(name);
+        self.entries.push((name.to_string(), value.into()));
+    }
+
+    /// 追加字段，保留同名旧值
+    pub fn append(&mut self, name: &str, value: impl Into<String>) {
+        self.entries.push((name.to_string(), value.into()));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    }
+
+    /// 判断逗号分隔的字段值中是否包含指定标记（不区分大小写）
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.entries
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+            .any(|(_, v)| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// 响应体
+pub enum Body {
+    Empty,
+    Bytes(Vec<u8>),
+    /// 从文件读取 `len` 字节
+    File(File, u64),
+    /// 任意字节流，长度未知时以分块编码发送
+    Stream(Pin<Box<dyn AsyncRead + Send>>, Option<u64>),
+}
+
+impl Body {
+    /// 已知的消息体长度
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Body::Empty => Some(0),
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::File(_, len) => Some(*len),
+            Body::Stream(_, len) => *len,
+        }
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Empty => f.write_str("Empty"),
+            Body::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            Body::File(_, len) => write!(f, "File({} bytes)", len),
+            Body::Stream(_, len) => write!(f, "Stream({:?})", len),
+        }
+    }
+}
+
+/// HTTP 响应
+///
+/// 处理函数构造并返回响应，由连接循环统一写出；`Date`、`Server`、
+/// `Content-Length` 与 `Connection` 头在写出时自动补全。
+#[derive(Debug)]
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Body,
+    /// HEAD 响应只写出头部，`Content-Length` 仍按消息体计算
+    omit_body: bool,
+    /// 由 [`Response::error`] 生成时的错误说明，用于替换为自定义错误页
+    detail: Option<String>,
+}
+
+impl Response {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: HeaderMap::default(),
+            body: Body::Empty,
+            omit_body: false,
+            detail: None,
+        }
+    }
+
+    /// 纯文本错误响应，禁止缓存
+    ///
+    /// 连接循环会按 `Accept` 将其替换为配置的 HTML 或 JSON 错误文档，见 [`crate::errors`]。
+    pub fn error(status: StatusCode, message: &str) -> Self {
+        let mut response = Self::new(status)
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .header("Cache-Control", "no-store")
+            .body(message.as_bytes().to_vec());
+        response.detail = Some(message.to_string());
+        response
+    }
+
+    /// 错误说明；消息体被处理函数替换过时返回 `None`
+    pub fn error_detail(&self) -> Option<&str> {
+        self.detail.as_deref()
+    }
+
+    /// 设置响应头，替换同名旧值
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// 追加响应头，保留同名旧值
+    pub fn append_header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Body::Bytes(body.into());
+        self.detail = None;
+        self
+    }
+
+    pub fn file(mut self, file: File, len: u64) -> Self {
+        self.body = Body::File(file, len);
+        self.detail = None;
+        self
+    }
+
+    pub fn stream(mut self, stream: impl AsyncRead + Send + 'static, len: Option<u64>) -> Self {
+        self.body = Body::Stream(Box::pin(stream), len);
+        self.detail = None;
+        self
+    }
+
+    /// 转为 HEAD 响应：保留与 GET 相同的头部，但不发送消息体
+    pub fn without_body(mut self) -> Self {
+        self.omit_body = true;
+        self
+    }
+
+    /// 响应体长度未知时，HTTP/1.0 只能以关闭连接表示消息结束
+    pub fn requires_close(&self, version: Version) -> bool {
+        !self.omit_body && self.body.len().is_none() && version == Version::Http10
+    }
+
+    /// 补全自动生成的响应头并序列化状态行和头部，返回是否使用分块编码
+    fn prepare(&mut self, version: Version) -> (Vec<u8>, bool) {
+        if !self.status.allows_body() {
+            self.body = Body::Empty;
+        }
+        let chunked = self.body.len().is_none() && version == Version::Http11 && !self.omit_body;
+
+        self.headers.insert("Date", http_date());
+        if !self.headers.contains("Server") {
+            self.headers.insert("Server", SERVER_NAME);
+        }
+        self.headers.remove("Transfer-Encoding");
+        match self.body.len() {
+            Some(len) if self.status.allows_body() => self.headers.insert("Content-Length", len.to_string()),
+            Some(_) => self.headers.remove("Content-Length"),
+            None if chunked => {
+                self.headers.remove("Content-Length");
+                self.headers.insert("Transfer-Encoding", "chunked");
+            }
+            None => self.headers.remove("Content-Length"),
+        }
+
+        let mut head = format!("HTTP/1.1 {}\r\n", self.status).into_bytes();
+        for (name, value) in self.headers.iter() {
+            head.extend_from_slice(name.as_bytes());
+            head.extend_from_slice(b": ");
+            head.extend_from_slice(value.as_bytes());
+            head.extend_from_slice(b"\r\n");
+        }
+        head.extend_from_slice(b"\r\n");
+
+        if self.omit_body {
+            self.body = Body::Empty;
+        }
+        (head, chunked)
+    }
+
+    /// 写出状态行、响应头和消息体
+    ///
+    /// 消息体以固定大小的缓冲区逐块写出，对端读取缓慢时写入随之等待。
+    /// `sent` 累计已写出的字节数（含头部），中途出错时同样准确。
+    pub async fn write_to<W>(mut self, writer: &mut W, version: Version, sent: &mut u64) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let (mut head, chunked) = self.prepare(version);
+        match self.body {
+            Body::Empty => write_counted(writer, &head, sent).await?,
+            Body::Bytes(bytes) => {
+                head.extend_from_slice(&bytes);
+                write_counted(writer, &head, sent).await?;
+            }
+            Body::File(file, len) => {
+                write_counted(writer, &head, sent).await?;
+                copy_exact(&mut file.take(len), writer, len, sent).await?;
+            }
+            Body::Stream(stream, Some(len)) => {
+                write_counted(writer, &head, sent).await?;
+                copy_exact(&mut stream.take(len), writer, len, sent).await?;
+            }
+            Body::Stream(stream, None) if chunked => {
+                write_counted(writer, &head, sent).await?;
+                write_chunked(stream, writer, sent).await?;
+            }
+            Body::Stream(mut stream, None) => {
+                write_counted(writer, &head, sent).await?;
+                copy_exact(&mut stream, writer, u64::MAX, sent).await?;
+            }
+        }
+        writer.flush().await
+    }
+
+    /// 写出到 TCP 连接；文件消息体在 Linux 上以 `sendfile` 零拷贝发送
+    pub async fn send_to(mut self, stream: &mut TcpStream, version: Version, sent: &mut u64) -> std::io::Result<()> {
+        #[cfg(target_os = "linux")]
+        if matches!(self.body, Body::File(..)) && !self.omit_body && self.status.allows_body() {
+            use tokio::io::AsyncSeekExt;
+            let (head, _) = self.prepare(version);
+            let Body::File(mut file, len) = std::mem::replace(&mut self.body, Body::Empty) else {
+                unreachable!("checked above");
+            };
+            write_counted(stream, &head, sent).await?;
+            let offset = file.stream_position().await?;
+            return sendfile::send(stream, &file, offset, len, sent).await;
+        }
+        self.write_to(stream, version, sent).await
+    }
+}
+
+/// 复制过程中使用的缓冲区大小
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+async fn write_counted<W>(writer: &mut W, bytes: &[u8], sent: &mut u64) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut written = 0;
+    while written < bytes.len() {
+        let n = writer.write(&bytes[written..]).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        written += n;
+        *sent += n as u64;
+    }
+    Ok(())
+}
+
+/// 复制至多 `len` 字节；已知长度的消息体提前结束时返回错误，避免连接上的报文错位
+async fn copy_exact<R, W>(reader: &mut R, writer: &mut W, len: u64, sent: &mut u64) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    let mut copied = 0;
+    while copied < len {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            if len == u64::MAX {
+                break;
+            }
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "response body ended before Content-Length",
+            ));
+        }
+        write_counted(writer, &buf[..n], sent).await?;
+        copied += n as u64;
+    }
+    Ok(())
+}
+
+/// 以分块传输编码写出字节流
+async fn write_chunked<W>(mut stream: Pin<Box<dyn AsyncRead + Send>>, writer: &mut W, sent: &mut u64) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        write_counted(writer, format!("{:X}\r\n", n).as_bytes(), sent).await?;
+        write_counted(writer, &buf[..n], sent).await?;
+        write_counted(writer, b"\r\n", sent).await?;
+    }
+    write_counted(writer, b"0\r\n\r\n", sent).await
+}
+
+#[cfg(target_os = "linux")]
+mod sendfile {
+    use std::io;
+    use std::os::fd::AsRawFd;
+    use tokio::fs::File;
+    use tokio::io::Interest;
+    use tokio::net::TcpStream;
+
+    /// 单次 `sendfile` 调用的最大字节数，让出执行权给同一线程上的其它连接
+    const CHUNK: u64 = 1024 * 1024;
+
+    /// 从文件的 `offset` 处发送 `len` 字节，套接字缓冲区满时等待可写
+    pub async fn send(stream: &TcpStream, file: &File, offset: u64, len: u64, sent: &mut u64) -> io::Result<()> {
+        let (out_fd, in_fd) = (stream.as_raw_fd(), file.as_raw_fd());
+        let mut offset = offset as libc::off_t;
+        let mut remaining = len;
+        while remaining > 0 {
+            stream.writable().await?;
+            let count = remaining.min(CHUNK) as usize;
+            let result = stream.try_io(Interest::WRITABLE, || {
+                // SAFETY: 两个描述符在调用期间由 `stream` 和 `file` 持有，offset 指向有效的局部变量
+                let n = unsafe { libc::sendfile(out_fd, in_fd, &mut offset, count) };
+                if n < 0 {
+                    Err(io::Error::last_os_error())
+                } else {
+                    Ok(n as u64)
+                }
+            });
+            match result {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "file was truncated while being sent",
+                    ));
+                }
+                Ok(n) => {
+                    remaining -= n;
+                    *sent += n;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// RFC 9110 §5.6.7 规定的 IMF-fixdate 格式
+pub fn http_date() -> String {
+    Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
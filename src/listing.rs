@@ -0,0 +1,202 @@
+use crate::http::{Response, StatusCode};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::io::Result;
+use std::path::Path;
+use tokio::fs;
+
+/// 目录列表中的一项
+#[derive(Debug, Serialize)]
+struct Entry {
+    name: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    /// 目录为 `None`
+    size: Option<u64>,
+    modified: Option<DateTime<Utc>>,
+}
+
+impl Entry {
+    fn is_dir(&self) -> bool {
+        self.kind == "directory"
+    }
+}
+
+/// 排序字段，由查询参数 `sort` 指定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Size,
+    Modified,
+}
+
+/// 生成目录列表
+///
+/// `url_path` 为请求中的目录路径（以 `/` 结尾）；查询参数 `sort=name|size|mtime`、
+/// `order=asc|desc` 控制排序，`format=json` 或只接受 JSON 的 `Accept` 返回 JSON。
+pub async fn directory_listing(
+    dir: &Path,
+    url_path: &str,
+    query: &[(String, String)],
+    accept: Option<&str>,
+) -> Result<Response> {
+    let param = |name: &str| query.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+    let sort = match param("sort") {
+        Some("size") => SortKey::Size,
+        Some("mtime") => SortKey::Modified,
+        _ => SortKey::Name,
+    };
+    let descending = param("order") == Some("desc");
+
+    let mut entries = read_entries(dir).await?;
+    entries.sort_by(|a, b| {
+        // 目录总是排在文件前面
+        b.is_dir().cmp(&a.is_dir()).then_with(|| {
+            let ordering = match sort {
+                SortKey::Name => Ordering::Equal,
+                SortKey::Size => a.size.cmp(&b.size),
+                SortKey::Modified => a.modified.cmp(&b.modified),
+            }
+            .then_with(|| a.name.cmp(&b.name));
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+    });
+
+    let wants_json = param("format") == Some("json")
+        || accept.is_some_and(|a| a.contains("application/json") && !a.contains("text/html"));
+    let response = if wants_json {
+        let body = serde_json::json!({ "path": url_path, "entries": entries });
+        Response::new(StatusCode::Ok)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+    } else {
+        Response::new(StatusCode::Ok)
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(render_html(url_path, &entries, sort, descending))
+    };
+    Ok(response
+        .header("Cache-Control", "no-cache")
+        .append_header("Vary", "Accept"))
+}
+
+async fn read_entries(dir: &Path) -> Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    let mut reader = fs::read_dir(dir).await?;
+    while let Some(entry) = reader.next_entry().await? {
+        // 跟随符号链接取目标的元数据，失效的链接直接跳过
+        let Ok(metadata) = fs::metadata(entry.path()).await else {
+            continue;
+        };
+        let is_dir = metadata.is_dir();
+        entries.push(Entry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            kind: if is_dir { "directory" } else { "file" },
+            size: if is_dir { None } else { Some(metadata.len()) },
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        });
+    }
+    Ok(entries)
+}
+
+fn render_html(url_path: &str, entries: &[Entry], sort: SortKey, descending: bool) -> String {
+    let title = format!("Index of {}", html_escape(url_path));
+    let mut html = String::from("<!DOCTYPE html><html><head>");
+    html.push_str(&format!(r#"<meta charset="utf-8"><title>{}</title><style>
+        body {{ font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; margin: 2rem; }}
+        h1 {{ color: #333; }}
+        table {{ width: 100%; border-collapse: collapse; margin-top: 1rem; }}
+        th, td {{ padding: 0.5rem 1rem; text-align: left; border-bottom: 1px solid #eee; }}
+        th {{ font-weight: 600; color: #555; }}
+        tr:hover {{ background-color: #f9f9f9; }}
+        .dir::before {{ content: "📁 "; }}
+        .file::before {{ content: "📄 "; }}
+        .size {{ color: #888; font-size: 0.9em; }}
+        a {{ text-decoration: none; color: #0070f3; }}
+        a:hover {{ text-decoration: underline; }}
+    </style></head><body><h1>{}</h1><table><thead><tr>"#, title, title));
+
+    // 点击当前排序列时切换升降序
+    for (label, key, param) in [
+        ("Name", SortKey::Name, "name"),
+        ("Size", SortKey::Size, "size"),
+        ("Modified", SortKey::Modified, "mtime"),
+    ] {
+        let order = if key == sort && !descending { "desc" } else { "asc" };
+        html.push_str(&format!(r#"<th><a href="?sort={}&amp;order={}">{}</a></th>"#, param, order, label));
+    }
+    html.push_str("</tr></thead><tbody>");
+
+    if url_path != "/" {
+        html.push_str(r#"<tr><td><a href="../" class="dir">../</a></td><td>-</td><td>-</td></tr>"#);
+    }
+    for entry in entries {
+        let (class, href, name) = if entry.is_dir() {
+            ("dir", format!("{}/", url_encode(&entry.name)), format!("{}/", entry.name))
+        } else {
+            ("file", url_encode(&entry.name), entry.name.clone())
+        };
+        let size = entry.size.map_or_else(|| "-".to_string(), human_size);
+        let modified = entry
+            .modified
+            .map_or_else(|| "-".to_string(), |m| m.format("%Y-%m-%d %H:%M:%S UTC").to_string());
+        html.push_str(&format!(
+            r#"<tr><td><a href="./{}" class="{}">{}</a></td><td class="size">{}</td><td>{}</td></tr>"#,
+            href,
+            class,
+            html_escape(&name),
+            size,
+            modified
+        ));
+    }
+
+    html.push_str("</tbody></table></body></html>");
+    html
+}
+
+/// 以 1024 为进制的可读大小，如 `1.5 KiB`
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+pub fn html_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 按 UTF-8 字节百分号编码路径段，只保留 RFC 3986 的非保留字符
+pub fn url_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+    encoded
+}
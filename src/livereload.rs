@@ -0,0 +1,231 @@
+use crate::http::{Body, Response, StatusCode};
+use crate::middleware::Next;
+use crate::router::Context;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{broadcast, mpsc};
+
+/// 推送文件变化事件的 Server-Sent Events 端点
+pub const EVENTS_PATH: &str = "/__livereload";
+/// 注入页面的客户端脚本
+pub const SCRIPT_PATH: &str = "/__livereload.js";
+
+/// 编辑器保存时往往连续产生多个事件，静默这么久之后才通知浏览器
+const DEBOUNCE: Duration = Duration::from_millis(100);
+/// SSE 注释行的发送间隔，用于及时发现已断开的浏览器
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+const SCRIPT: &str = r#"(() => {
+  const source = new EventSource("/__livereload");
+  source.addEventListener("reload", () => location.reload());
+  // 只有样式表变化时替换对应的 <link>，页面状态得以保留
+  source.addEventListener("css", (event) => {
+    const links = [...document.querySelectorAll('link[rel="stylesheet"]')].filter((link) => {
+      const url = new URL(link.href);
+      return url.origin === location.origin && url.pathname === event.data;
+    });
+    if (links.length === 0) {
+      location.reload();
+      return;
+    }
+    for (const link of links) {
+      const url = new URL(link.href);
+      url.searchParams.set("livereload", Date.now());
+      const fresh = link.cloneNode();
+      fresh.href = url.href;
+      fresh.addEventListener("load", () => link.remove());
+      link.after(fresh);
+    }
+  });
+})();
+"#;
+
+/// 通知浏览器的事件
+#[derive(Debug, Clone)]
+enum Event {
+    /// 重新加载整个页面
+    Reload,
+    /// 只替换该 URL 路径的样式表
+    Css(String),
+}
+
+impl Event {
+    fn message(&self) -> String {
+        match self {
+            Event::Reload => "event: reload\ndata: \n\n".to_string(),
+            Event::Css(path) => format!("event: css\ndata: {}\n\n", path),
+        }
+    }
+}
+
+/// 开发模式下监视静态文件根目录，把变化广播给所有打开的页面
+///
+/// 在 Linux 上通过 inotify 递归监视；监视失败时只记录错误，页面不会自动刷新。
+pub struct LiveReload {
+    events: broadcast::Sender<Event>,
+    /// 丢弃时停止监视
+    _watcher: Option<RecommendedWatcher>,
+}
+
+impl LiveReload {
+    /// 开始监视 `root`，须在 Tokio 运行时中调用
+    pub fn start(root: &Path) -> Self {
+        let (events, _) = broadcast::channel(16);
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let (changes, receiver) = mpsc::unbounded_channel();
+        let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            if let Ok(event) = result
+                && !matches!(event.kind, EventKind::Access(_))
+            {
+                for path in event.paths {
+                    let _ = changes.send(path);
+                }
+            }
+        })
+        .and_then(|mut watcher| watcher.watch(&root, RecursiveMode::Recursive).map(|_| watcher));
+        let watcher = match watcher {
+            Ok(watcher) => {
+                println!("Live reload is watching {}", root.display());
+                tokio::spawn(debounce(root, receiver, events.clone()));
+                Some(watcher)
+            }
+            Err(e) => {
+                eprintln!("Cannot watch {} for live reload: {}", root.display(), e);
+                None
+            }
+        };
+        Self {
+            events,
+            _watcher: watcher,
+        }
+    }
+}
+
+/// 合并一小段时间内的变化：全部是样式表时逐个热替换，否则刷新页面
+async fn debounce(root: PathBuf, mut changes: mpsc::UnboundedReceiver<PathBuf>, events: broadcast::Sender<Event>) {
+    while let Some(first) = changes.recv().await {
+        let mut paths = vec![first];
+        while let Ok(Some(path)) = tokio::time::timeout(DEBOUNCE, changes.recv()).await {
+            paths.push(path);
+        }
+        let mut urls: Vec<String> = paths.iter().filter_map(|path| url_path(&root, path)).collect();
+        urls.sort();
+        urls.dedup();
+        if urls.is_empty() {
+            continue;
+        }
+        crate::utils::debug(&format!("Live reload: {} changed", urls.join(", ")));
+        if urls.iter().all(|url| url.ends_with(".css")) {
+            for url in urls {
+                let _ = events.send(Event::Css(url));
+            }
+        } else {
+            let _ = events.send(Event::Reload);
+        }
+    }
+}
+
+/// 文件对应的 URL 路径；隐藏文件和编辑器的临时文件返回 `None`
+fn url_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let mut url = String::new();
+    for component in relative.components() {
+        let name = component.as_os_str().to_str()?;
+        if name.starts_with('.') || name.ends_with('~') || name.ends_with(".swp") {
+            return None;
+        }
+        url.push('/');
+        url.push_str(name);
+    }
+    (!url.is_empty()).then_some(url)
+}
+
+/// 以 `text/event-stream` 持续推送事件，直到浏览器断开或配置重新加载
+pub async fn handle_events(live: Arc<LiveReload>, _ctx: Context) -> Response {
+    let mut events = live.events.subscribe();
+    let (mut writer, reader) = tokio::io::duplex(4096);
+    tokio::spawn(async move {
+        if writer.write_all(b"retry: 1000\n\n").await.is_err() {
+            return;
+        }
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        loop {
+            let message = tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => event.message(),
+                    Err(broadcast::error::RecvError::Lagged(_)) => Event::Reload.message(),
+                    Err(broadcast::error::RecvError::Closed) => return,
+                },
+                _ = ping.tick() => ": ping\n\n".to_string(),
+            };
+            if writer.write_all(message.as_bytes()).await.is_err() {
+                return;
+            }
+        }
+    });
+    // no-transform 防止压缩中间件缓冲事件
+    Response::new(StatusCode::Ok)
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache, no-transform")
+        .stream(reader, None)
+}
+
+pub async fn handle_script(_ctx: Context) -> Response {
+    Response::new(StatusCode::Ok)
+        .header("Content-Type", "application/javascript; charset=utf-8")
+        .header("Cache-Control", "no-cache")
+        .body(SCRIPT)
+}
+
+/// 在 HTML 页面的 `</body>` 之前插入客户端脚本，没有该标签时追加到末尾
+///
+/// 已编码的响应和长度未知的流保持原样；页面随文件变化，每次都向服务器验证。
+pub async fn inject_script(ctx: Context, next: Next) -> Response {
+    let mut response = next.run(ctx).await;
+    let is_html = response
+        .headers
+        .get("Content-Type")
+        .is_some_and(|t| t.trim_start().to_ascii_lowercase().starts_with("text/html"));
+    if response.status != StatusCode::Ok || !is_html || response.headers.contains("Content-Encoding") {
+        return response;
+    }
+    let page = match std::mem::replace(&mut response.body, Body::Empty) {
+        Body::Bytes(bytes) => Ok(bytes),
+        Body::File(file, len) => read_all(file.take(len)).await,
+        Body::Stream(stream, Some(len)) => read_all(stream.take(len)).await,
+        body => {
+            response.body = body;
+            return response;
+        }
+    };
+    let Ok(mut page) = page else {
+        return Response::error(StatusCode::InternalServerError, "Error reading file");
+    };
+
+    let tag = format!("<script src=\"{}\"></script>\n", SCRIPT_PATH);
+    let lowercase = page.to_ascii_lowercase();
+    let at = lowercase
+        .windows(b"</body>".len())
+        .rposition(|w| w == b"</body>")
+        .unwrap_or(page.len());
+    page.splice(at..at, tag.into_bytes());
+    response.body = Body::Bytes(page);
+    response.headers.insert("Cache-Control", "no-cache");
+    // 字节范围针对原文件，与注入后的页面对不上
+    response.headers.remove("Accept-Ranges");
+    // 注入后的字节与原文件不同，ETag 只能作为弱验证器
+    if let Some(etag) = response.headers.get("ETag").filter(|e| !e.starts_with("W/")) {
+        let weak = format!("W/{}", etag);
+        response.headers.insert("ETag", weak);
+    }
+    response
+}
+
+async fn read_all(mut reader: impl tokio::io::AsyncRead + Unpin) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+    Ok(bytes)
+}
@@ -1,12 +1,44 @@
-use std::io::{Read, Write};
-use std::net::TcpListener;
+mod cli;
+mod compression;
+mod conditional;
+mod config;
+mod errors;
+mod file_cache;
+mod handlers;
+mod http;
+mod listing;
+mod livereload;
+mod middleware;
+mod mime;
+mod range;
+mod router;
+mod server;
+mod site;
+mod tls;
+mod utils;
 
-fn main() {
-    let socket = "127.0.0.1:50000";
-    let source = "~/Projects/web-client-node";
+use clap::Parser;
 
-    let listener = TcpListener::bind(socket).unwrap();
-    println!("正在监听 Tcp 套接字 {}", socket);
+fn main() -> std::io::Result<()> {
+    let source = config::ConfigSource::new(cli::Cli::parse());
+    let config = match source.load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Configuration error: {}", e);
+            std::process::exit(2);
+        }
+    };
 
-    for stream in listener.incoming() {}
+    utils::init_logging(config.log_settings());
+
+    let mut runtime = tokio::runtime::Builder::new_multi_thread();
+    runtime.enable_all();
+    if let Some(workers) = config.server.workers {
+        runtime.worker_threads(workers);
+    }
+
+    runtime.build()?.block_on(async {
+        let server = server::Server::new(config, source).await?;
+        server.run().await
+    })
 }
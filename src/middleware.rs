@@ -0,0 +1,200 @@
+use crate::compression::{self, Encoding};
+use crate::config::{CompressionConfig, CorsConfig};
+use crate::http::{Body, Method, Response, StatusCode};
+use crate::router::{BoxFuture, Context, Handler};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::io::AsyncReadExt;
+
+/// 中间件：可以在路由前检查或修改请求，在路由后修改响应，也可以不调用 `next` 直接返回响应
+///
+/// 任何 `async fn(Context, Next) -> Response` 都实现了该 trait。
+pub trait Middleware: Send + Sync + 'static {
+    fn handle(&self, ctx: Context, next: Next) -> BoxFuture;
+}
+
+impl<F, Fut> Middleware for F
+where
+    F: Fn(Context, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    fn handle(&self, ctx: Context, next: Next) -> BoxFuture {
+        Box::pin(self(ctx, next))
+    }
+}
+
+/// 中间件链中剩余的部分，按注册顺序由外向内执行，最后调用处理函数
+#[derive(Clone)]
+pub struct Next {
+    chain: Arc<[Arc<dyn Middleware>]>,
+    index: usize,
+    endpoint: Arc<dyn Handler>,
+}
+
+impl Next {
+    pub fn new(chain: Vec<Arc<dyn Middleware>>, endpoint: Arc<dyn Handler>) -> Self {
+        Self {
+            chain: chain.into(),
+            index: 0,
+            endpoint,
+        }
+    }
+
+    /// 调用下一个中间件，链尾时调用处理函数
+    pub fn run(self, ctx: Context) -> BoxFuture {
+        match self.chain.get(self.index).cloned() {
+            Some(middleware) => {
+                let next = Next {
+                    index: self.index + 1,
+                    ..self
+                };
+                middleware.handle(ctx, next)
+            }
+            None => self.endpoint.call(ctx),
+        }
+    }
+}
+
+/// 跨域资源共享，按配置的来源列表添加 `Access-Control-*` 响应头
+pub struct Cors {
+    config: Arc<CorsConfig>,
+}
+
+impl Cors {
+    pub fn new(config: CorsConfig) -> Self {
+        Self { config: Arc::new(config) }
+    }
+}
+
+impl Middleware for Cors {
+    fn handle(&self, ctx: Context, next: Next) -> BoxFuture {
+        let config = Arc::clone(&self.config);
+        Box::pin(async move {
+            let origin = ctx.request.headers.get("origin").map(str::to_string);
+            let preflight = ctx.request.method == Method::Options
+                && ctx.request.headers.contains("access-control-request-method");
+            let request_headers = ctx
+                .request
+                .headers
+                .get("access-control-request-headers")
+                .map(str::to_string);
+
+            let mut response = next.run(ctx).await;
+            let Some(origin) = origin.filter(|o| config.allow_origins.iter().any(|a| a == "*" || a == o)) else {
+                return response;
+            };
+
+            let wildcard = config.allow_origins.iter().any(|o| o == "*") && !config.allow_credentials;
+            response.headers.insert(
+                "Access-Control-Allow-Origin",
+                if wildcard { "*".to_string() } else { origin },
+            );
+            if !wildcard {
+                response.headers.append("Vary", "Origin");
+            }
+            if config.allow_credentials {
+                response.headers.insert("Access-Control-Allow-Credentials", "true");
+            }
+
+            if preflight && response.status == StatusCode::NoContent {
+                if let Some(allow) = response.headers.get("Allow").map(str::to_string) {
+                    response.headers.insert("Access-Control-Allow-Methods", allow);
+                }
+                if let Some(headers) = request_headers {
+                    response.headers.insert("Access-Control-Allow-Headers", headers);
+                }
+                response.headers.insert("Access-Control-Max-Age", config.max_age.to_string());
+            } else if !config.expose_headers.is_empty() {
+                response
+                    .headers
+                    .insert("Access-Control-Expose-Headers", config.expose_headers.join(", "));
+            }
+            response
+        })
+    }
+}
+
+/// 按 `Accept-Encoding` 压缩响应体
+///
+/// 带 `Range` 的请求、分段响应和已编码的响应保持原样；压缩后的表示使用带编码后缀的 ETag，
+/// 客户端回传的条件请求头在交给处理函数前还原。
+pub struct Compression {
+    config: Arc<CompressionConfig>,
+}
+
+impl Compression {
+    pub fn new(config: CompressionConfig) -> Self {
+        Self { config: Arc::new(config) }
+    }
+}
+
+impl Middleware for Compression {
+    fn handle(&self, mut ctx: Context, next: Next) -> BoxFuture {
+        let config = Arc::clone(&self.config);
+        Box::pin(async move {
+            let encoding = compression::requested(&ctx.request.headers);
+            let mut restored = false;
+            if let Some(encoding) = encoding {
+                for name in ["if-none-match", "if-match"] {
+                    if let Some(list) = ctx.request.headers.get(name).and_then(|v| compression::strip_encoding(v, encoding)) {
+                        ctx.request.headers.remove(name);
+                        ctx.request.headers.append(name, &list);
+                        restored = true;
+                    }
+                }
+            }
+
+            let mut response = next.run(ctx).await;
+            // 304 对应客户端缓存的压缩表示，ETag 同样带上编码后缀
+            if response.status == StatusCode::NotModified {
+                if let (true, Some(encoding)) = (restored, encoding) {
+                    tag_encoded(&mut response, encoding);
+                }
+                return response;
+            }
+            if !compression::is_transformable(&response, &config) {
+                return response;
+            }
+            if !response.headers.has_token("Vary", "Accept-Encoding") {
+                response.headers.append("Vary", "Accept-Encoding");
+            }
+            let Some(encoding) = encoding else {
+                return response;
+            };
+            if response.body.len().is_some_and(|len| len < config.min_size) {
+                return response;
+            }
+
+            response.body = match std::mem::replace(&mut response.body, Body::Empty) {
+                Body::Bytes(bytes) => match compression::encode_bytes(bytes, encoding, &config).await {
+                    Ok(encoded) => Body::Bytes(encoded),
+                    Err(_) => return Response::error(StatusCode::InternalServerError, "500 Internal Server Error"),
+                },
+                Body::File(file, len) => {
+                    Body::Stream(Box::pin(compression::encode_stream(file.take(len), encoding, &config)), None)
+                }
+                Body::Stream(stream, Some(len)) => {
+                    Body::Stream(Box::pin(compression::encode_stream(stream.take(len), encoding, &config)), None)
+                }
+                Body::Stream(stream, None) => {
+                    Body::Stream(Box::pin(compression::encode_stream(stream, encoding, &config)), None)
+                }
+                Body::Empty => Body::Empty,
+            };
+            tag_encoded(&mut response, encoding);
+            response.headers.insert("Content-Encoding", encoding.as_str());
+            // 压缩后的表示不支持按字节范围续传
+            response.headers.remove("Accept-Ranges");
+            response
+        })
+    }
+}
+
+fn tag_encoded(response: &mut Response, encoding: Encoding) {
+    if let Some(etag) = response.headers.get("ETag").map(|e| compression::etag_with_encoding(e, encoding)) {
+        response.headers.insert("ETag", etag);
+    }
+    if !response.headers.has_token("Vary", "Accept-Encoding") {
+        response.headers.append("Vary", "Accept-Encoding");
+    }
+}
@@ -0,0 +1,182 @@
+use crate::config::Config;
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+
+/// 无法识别时使用的类型
+pub const OCTET_STREAM: &str = "application/octet-stream";
+
+/// 内容嗅探最多读取的字节数
+const SNIFF_LENGTH: usize = 512;
+
+/// 确定静态文件的 `Content-Type`
+///
+/// 配置中的覆盖优先于内置表；没有扩展名的文件在启用 `server.sniff_mime` 时按内容判断。
+/// 文本类型统一补上 `charset=utf-8`。
+pub async fn for_file(path: &Path, config: &Config) -> String {
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    let mime = match &extension {
+        Some(ext) => match config.mime.get(ext) {
+            Some(mime) => mime.as_str(),
+            None => builtin(ext).unwrap_or(OCTET_STREAM),
+        },
+        None if config.server.sniff_mime => sniff(path).await,
+        None => OCTET_STREAM,
+    };
+    with_charset(mime)
+}
+
+/// 文本类型缺少 `charset` 参数时补上 UTF-8
+pub fn with_charset(mime: &str) -> String {
+    let essence = mime.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    let textual = essence.starts_with("text/")
+        || matches!(
+            essence.as_str(),
+            "application/javascript" | "application/json" | "application/xml" | "application/manifest+json"
+        )
+        || essence.ends_with("+xml") && essence != "image/svg+xml";
+    if textual && !mime.to_ascii_lowercase().contains("charset=") {
+        format!("{}; charset=utf-8", mime)
+    } else {
+        mime.to_string()
+    }
+}
+
+/// 内置的扩展名对照表，扩展名须为小写
+pub fn builtin(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        // 文本与网页
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" | "cjs" => "application/javascript",
+        "json" | "map" => "application/json",
+        "jsonld" => "application/ld+json",
+        "webmanifest" => "application/manifest+json",
+        "xml" => "application/xml",
+        "xhtml" => "application/xhtml+xml",
+        "rss" => "application/rss+xml",
+        "atom" => "application/atom+xml",
+        "txt" | "text" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "csv" => "text/csv",
+        "tsv" => "text/tab-separated-values",
+        "ics" => "text/calendar",
+        "vtt" => "text/vtt",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        // 图片
+        "png" => "image/png",
+        "jpg" | "jpeg" | "jpe" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "svg" | "svgz" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "heic" => "image/heic",
+        // 字体
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "eot" => "application/vnd.ms-fontobject",
+        // 音视频
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "ogv" => "video/ogg",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "avi" => "video/x-msvideo",
+        "m3u8" => "application/vnd.apple.mpegurl",
+        "ts" => "video/mp2t",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "aac" => "audio/aac",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "weba" => "audio/webm",
+        // 应用与归档
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "bz2" => "application/x-bzip2",
+        "xz" => "application/x-xz",
+        "7z" => "application/x-7z-compressed",
+        "rar" => "application/vnd.rar",
+        "br" => "application/x-brotli",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "epub" => "application/epub+zip",
+        "apk" => "application/vnd.android.package-archive",
+        "bin" | "exe" | "dll" | "iso" | "dmg" => OCTET_STREAM,
+        _ => return None,
+    })
+}
+
+/// 按文件开头的特征字节判断类型，读取失败时视为二进制
+async fn sniff(path: &Path) -> &'static str {
+    let mut buf = vec![0; SNIFF_LENGTH];
+    let len = match File::open(path).await {
+        Ok(mut file) => file.read(&mut buf).await.unwrap_or(0),
+        Err(_) => return OCTET_STREAM,
+    };
+    sniff_bytes(&buf[..len])
+}
+
+fn sniff_bytes(bytes: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\0asm", "application/wasm"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+    ];
+    if let Some((_, mime)) = SIGNATURES.iter().find(|(magic, _)| bytes.starts_with(magic)) {
+        return mime;
+    }
+    // RIFF 容器和 ISO 基础媒体文件格式的类型标记不在开头
+    match (bytes.get(..4), bytes.get(8..12)) {
+        (Some(b"RIFF"), Some(b"WEBP")) => return "image/webp",
+        (Some(b"RIFF"), Some(b"WAVE")) => return "audio/wav",
+        _ => {}
+    }
+    if bytes.get(4..8) == Some(b"ftyp") {
+        return "video/mp4";
+    }
+
+    // 文本：不含 NUL 且是合法 UTF-8（末尾可能截断在多字节字符中间）
+    if bytes.contains(&0) {
+        return OCTET_STREAM;
+    }
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or(""),
+        Err(_) => return OCTET_STREAM,
+    };
+    let start = text.trim_start().get(..14).unwrap_or(text.trim_start()).to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "text/html"
+    } else if start.starts_with("<?xml") {
+        "application/xml"
+    } else {
+        "text/plain"
+    }
+}
@@ -0,0 +1,250 @@
+use crate::conditional::{Validators, etag_matches};
+use crate::http::{Response, StatusCode};
+use std::io::{Cursor, Result, SeekFrom};
+use std::path::Path;
+use std::pin::Pin;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+
+/// 合并后最多允许的区间数，超过时忽略 `Range` 返回完整内容
+const MAX_RANGES: usize = 16;
+
+/// 闭区间 `[start, end]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+}
+
+/// `Range` 请求头的解析结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ranges {
+    /// 请求头无效或无需分段，返回完整内容
+    Full,
+    Partial(Vec<ByteRange>),
+    /// 416 Range Not Satisfiable
+    Unsatisfiable,
+}
+
+/// 按 RFC 9110 §14.1.2 解析 `bytes=` 区间集合，语法错误时整个请求头被忽略
+pub fn parse(header: &str, len: u64) -> Ranges {
+    let Some(specs) = header.trim().strip_prefix("bytes=") else {
+        return Ranges::Full;
+    };
+    let specs: Vec<&str> = specs.split(',').map(str::trim).filter(|s| !s.is_empty()).collect();
+    if specs.is_empty() {
+        return Ranges::Full;
+    }
+    let mut ranges = Vec::new();
+    // 空文件的非零后缀区间可以满足，但无法用 `Content-Range` 表示，只能返回完整的空内容
+    let mut empty_suffix = false;
+    for spec in specs {
+        let Some((first, last)) = spec.split_once('-') else {
+            return Ranges::Full;
+        };
+        let range = match (position(first), position(last)) {
+            // 后缀区间：最后 n 个字节
+            (None, Some(n)) if first.is_empty() => {
+                if n == 0 || len == 0 {
+                    empty_suffix |= n > 0;
+                    continue;
+                }
+                ByteRange {
+                    start: len.saturating_sub(n),
+                    end: len - 1,
+                }
+            }
+            (Some(start), None) if last.is_empty() => ByteRange { start, end: u64::MAX },
+            (Some(start), Some(end)) if end >= start => ByteRange { start, end },
+            _ => return Ranges::Full,
+        };
+        // 起点超出文件长度的区间不可满足，终点超出时截断
+        if range.start < len {
+            ranges.push(ByteRange {
+                start: range.start,
+                end: range.end.min(len - 1),
+            });
+        }
+    }
+    if ranges.is_empty() {
+        return if empty_suffix { Ranges::Full } else { Ranges::Unsatisfiable };
+    }
+
+    // 合并重叠或相邻的区间，防止用大量小区间放大响应
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    if merged.len() > MAX_RANGES {
+        return Ranges::Full;
+    }
+    Ranges::Partial(merged)
+}
+
+/// 区间端点只能是十进制数字
+fn position(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// `If-Range` 校验：实体标签须强匹配，日期须与 `Last-Modified` 完全一致
+pub fn if_range_matches(value: &str, validators: &Validators) -> bool {
+    let value = value.trim();
+    if value.starts_with('"') || value.starts_with("W/") {
+        return !value.starts_with("W/") && etag_matches(value, validators.etag.as_deref(), true);
+    }
+    match (httpdate::parse_http_date(value), validators.last_modified) {
+        (Ok(date), Some(modified)) => date == modified,
+        _ => false,
+    }
+}
+
+/// 416 响应，`Content-Range` 告知完整长度
+pub fn unsatisfiable(len: u64) -> Response {
+    Response::error(StatusCode::RangeNotSatisfiable, "Range Not Satisfiable")
+        .header("Content-Range", format!("bytes */{}", len))
+}
+
+/// 206 响应：单个区间直接返回文件片段，多个区间以 `multipart/byteranges` 返回
+pub async fn partial(path: &Path, ranges: &[ByteRange], len: u64, content_type: &str) -> Result<Response> {
+    let response = Response::new(StatusCode::PartialContent);
+    if let [range] = ranges {
+        let file = open_at(path, range.start).await?;
+        return Ok(response
+            .header("Content-Type", content_type)
+            .header("Content-Range", format!("bytes {}-{}/{}", range.start, range.end, len))
+            .file(file, range.len()));
+    }
+
+    let boundary = boundary(len);
+    let mut total = 0;
+    let mut body: Pin<Box<dyn AsyncRead + Send>> = Box::pin(tokio::io::empty());
+    for range in ranges {
+        let head = format!(
+            "\r\n--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+            boundary, content_type, range.start, range.end, len
+        );
+        total += head.len() as u64 + range.len();
+        let file = open_at(path, range.start).await?;
+        body = Box::pin(body.chain(Cursor::new(head)).chain(file.take(range.len())));
+    }
+    let tail = format!("\r\n--{}--\r\n", boundary);
+    total += tail.len() as u64;
+    let body = body.chain(Cursor::new(tail));
+
+    Ok(response
+        .header("Content-Type", format!("multipart/byteranges; boundary={}", boundary))
+        .stream(body, Some(total)))
+}
+
+async fn open_at(path: &Path, offset: u64) -> Result<File> {
+    let mut file = File::open(path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    Ok(file)
+}
+
+/// 分段边界只需在响应中不与内容冲突，取当前时间与文件长度混合
+fn boundary(len: u64) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64);
+    format!("{:016x}{:08x}", nanos ^ len.rotate_left(32), len as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn partial(ranges: &[(u64, u64)]) -> Ranges {
+        Ranges::Partial(ranges.iter().map(|&(start, end)| ByteRange { start, end }).collect())
+    }
+
+    #[test]
+    fn parses_single_ranges() {
+        assert_eq!(parse("bytes=0-4", 10), partial(&[(0, 4)]));
+        assert_eq!(parse("bytes=5-100", 10), partial(&[(5, 9)]));
+        // 从某处到末尾
+        assert_eq!(parse("bytes=5-", 10), partial(&[(5, 9)]));
+        assert_eq!(parse("bytes=9-", 10), partial(&[(9, 9)]));
+        assert_eq!(parse("bytes=10-", 10), Ranges::Unsatisfiable);
+        assert_eq!(parse("bytes=10-20", 10), Ranges::Unsatisfiable);
+        // 最后 n 个字节，超过文件长度时取整个文件
+        assert_eq!(parse("bytes=-3", 10), partial(&[(7, 9)]));
+        assert_eq!(parse("bytes=-20", 10), partial(&[(0, 9)]));
+        assert_eq!(parse("bytes=-0", 10), Ranges::Unsatisfiable);
+        assert_eq!(parse("bytes=-0,0-1", 10), partial(&[(0, 1)]));
+    }
+
+    #[test]
+    fn merges_overlapping_and_adjacent_ranges() {
+        assert_eq!(parse("bytes=0-4,3-6,8-9", 10), partial(&[(0, 6), (8, 9)]));
+        assert_eq!(parse("bytes=0-4,5-6", 10), partial(&[(0, 6)]));
+        assert_eq!(parse("bytes=8-9, 0-1", 10), partial(&[(0, 1), (8, 9)]));
+        assert_eq!(parse("bytes=-2,0-", 10), partial(&[(0, 9)]));
+        let repeated = vec!["0-1"; 1000].join(",");
+        assert_eq!(parse(&format!("bytes={}", repeated), 10), partial(&[(0, 1)]));
+    }
+
+    #[test]
+    fn ignores_too_many_ranges() {
+        let spec = |n: u64| (0..n).map(|i| format!("{}-{}", i * 2, i * 2)).collect::<Vec<_>>().join(",");
+        assert!(matches!(parse(&format!("bytes={}", spec(16)), 100), Ranges::Partial(r) if r.len() == 16));
+        assert_eq!(parse(&format!("bytes={}", spec(17)), 100), Ranges::Full);
+    }
+
+    #[test]
+    fn ignores_invalid_syntax() {
+        for header in [
+            "items=0-1",
+            "bytes=",
+            "bytes=,",
+            "bytes=5-1",
+            "bytes=a-b",
+            "bytes=0-1,x",
+            "bytes=+1-2",
+            "bytes=1",
+        ] {
+            assert_eq!(parse(header, 10), Ranges::Full, "{}", header);
+        }
+    }
+
+    #[test]
+    fn zero_length_files() {
+        assert_eq!(parse("bytes=0-", 0), Ranges::Unsatisfiable);
+        assert_eq!(parse("bytes=0-0", 0), Ranges::Unsatisfiable);
+        assert_eq!(parse("bytes=-0", 0), Ranges::Unsatisfiable);
+        assert_eq!(parse("bytes=-5", 0), Ranges::Full);
+    }
+
+    #[test]
+    fn if_range_requires_a_strong_or_exact_validator() {
+        let modified = httpdate::parse_http_date("Wed, 01 Jan 2025 00:00:00 GMT").unwrap();
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some(modified),
+        };
+        assert!(if_range_matches("\"abc\"", &validators));
+        assert!(!if_range_matches("W/\"abc\"", &validators));
+        assert!(!if_range_matches("\"x\"", &validators));
+        assert!(if_range_matches("Wed, 01 Jan 2025 00:00:00 GMT", &validators));
+        assert!(!if_range_matches("Wed, 01 Jan 2025 00:00:01 GMT", &validators));
+        let weak = Validators {
+            etag: Some("W/\"abc\"".to_string()),
+            last_modified: None,
+        };
+        assert!(!if_range_matches("\"abc\"", &weak));
+    }
+}
@@ -0,0 +1,690 @@
+use crate::config::Config;
+use crate::http::{Method, Request, Response, StatusCode};
+use crate::middleware::{Middleware, Next};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// 处理函数返回的异步响应
+pub type BoxFuture = Pin<Box<dyn Future<Output = Response> + Send>>;
+
+/// 传给处理函数的请求上下文
+pub struct Context {
+    pub request: Request,
+    /// 从路由模式中提取的路径参数
+    pub params: Params,
+    pub config: Arc<Config>,
+}
+
+/// 路由处理函数，任何 `async fn(Context) -> Response` 都实现了该 trait
+pub trait Handler: Send + Sync + 'static {
+    fn call(&self, ctx: Context) -> BoxFuture;
+}
+
+impl<F, Fut> Handler for F
+where
+    F: Fn(Context) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Response> + Send + 'static,
+{
+    fn call(&self, ctx: Context) -> BoxFuture {
+        Box::pin(self(ctx))
+    }
+}
+
+/// 路径参数，`:name` 对应单个路径段，`*name` 对应剩余的全部路径
+#[derive(Debug, Clone, Default)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    #[cfg_attr(not(test), expect(dead_code))]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+/// 路由模式，如 `/api/users/:id` 或 `/assets/*path`
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let Some(rest) = pattern.strip_prefix('/') else {
+            return Err("must start with `/`".to_string());
+        };
+        let mut segments = Vec::new();
+        let parts: Vec<&str> = if rest.is_empty() { vec![] } else { rest.split('/').collect() };
+        for (i, part) in parts.iter().enumerate() {
+            let segment = if let Some(name) = part.strip_prefix(':') {
+                if name.is_empty() {
+                    return Err(format!("parameter in segment {} has no name", i + 1));
+                }
+                Segment::Param(name.to_string())
+            } else if let Some(name) = part.strip_prefix('*') {
+                if i + 1 != parts.len() {
+                    return Err("a wildcard must be the last segment".to_string());
+                }
+                Segment::Wildcard(name.to_string())
+            } else {
+                Segment::Static(part.to_string())
+            };
+            segments.push(segment);
+        }
+        Ok(Self { segments })
+    }
+
+    /// 匹配成功时返回提取的路径参数
+    fn matches(&self, path: &str) -> Option<Params> {
+        let mut parts = path.strip_prefix('/')?.split('/').peekable();
+        if parts.peek() == Some(&"") {
+            parts.next();
+        }
+        let mut params = Vec::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Static(expected) => {
+                    if parts.next()? != expected {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    let value = parts.next().filter(|v| !v.is_empty())?;
+                    params.push((name.clone(), value.to_string()));
+                }
+                Segment::Wildcard(name) => {
+                    let rest: Vec<&str> = parts.by_ref().collect();
+                    params.push((name.clone(), rest.join("/")));
+                }
+            }
+        }
+        // 允许末尾多一个斜杠
+        match (parts.next(), parts.next()) {
+            (None, _) | (Some(""), None) => Some(Params(params)),
+            _ => None,
+        }
+    }
+
+    /// 越具体的模式优先：静态段 > 参数段 > 通配段
+    fn specificity(&self) -> Vec<u8> {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Static(_) => 2,
+                Segment::Param(_) => 1,
+                Segment::Wildcard(_) => 0,
+            })
+            .collect()
+    }
+}
+
+/// 单条路由
+pub struct Route {
+    methods: Vec<Method>,
+    pattern: Pattern,
+    handler: Arc<dyn Handler>,
+    max_body_size: Option<usize>,
+    /// 所在分组挂载的中间件，由外向内
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Route {
+    /// 覆盖该路由的请求体大小上限
+    pub fn max_body_size(&mut self, limit: Option<usize>) -> &mut Self {
+        self.max_body_size = limit;
+        self
+    }
+}
+
+/// 路由查找结果
+pub enum Resolution {
+    Matched {
+        handler: Arc<dyn Handler>,
+        params: Params,
+        max_body_size: Option<usize>,
+        /// 路由所在分组的中间件，在全局中间件之内执行
+        middleware: Vec<Arc<dyn Middleware>>,
+    },
+    /// 路径存在但方法不匹配，附带允许的方法
+    MethodNotAllowed(Vec<Method>),
+    NotFound,
+}
+
+/// 注册路由的公共接口，由 [`Router`] 和 [`Group`] 实现
+pub trait Routes {
+    /// 为多个方法注册同一处理函数；模式无效时 panic
+    fn route(&mut self, methods: &[Method], path: &str, handler: impl Handler) -> &mut Route;
+
+    fn get(&mut self, path: &str, handler: impl Handler) -> &mut Route {
+        self.route(&[Method::Get], path, handler)
+    }
+
+    fn post(&mut self, path: &str, handler: impl Handler) -> &mut Route {
+        self.route(&[Method::Post], path, handler)
+    }
+
+    #[cfg_attr(not(test), expect(dead_code))]
+    fn put(&mut self, path: &str, handler: impl Handler) -> &mut Route {
+        self.route(&[Method::Put], path, handler)
+    }
+
+    #[cfg_attr(not(test), expect(dead_code))]
+    fn delete(&mut self, path: &str, handler: impl Handler) -> &mut Route {
+        self.route(&[Method::Delete], path, handler)
+    }
+
+    #[cfg_attr(not(test), expect(dead_code))]
+    fn patch(&mut self, path: &str, handler: impl Handler) -> &mut Route {
+        self.route(&[Method::Patch], path, handler)
+    }
+}
+
+/// 按方法和路径模式分发请求
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    fallback: Option<Arc<dyn Handler>>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    /// 保留给 API 的路径前缀，其下没有匹配的路由时直接 404
+    reserved: Vec<String>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 没有路由匹配时处理 GET/HEAD 请求，例如静态文件
+    pub fn fallback(&mut self, handler: impl Handler) -> &mut Self {
+        self.fallback = Some(Arc::new(handler));
+        self
+    }
+
+    /// 保留路径前缀：前缀下的请求只交给显式路由，不交给兜底处理函数
+    ///
+    /// 例如保留 `/api` 后，`GET /api/unknown` 返回 404，而不会被当作静态文件或单页应用路由。
+    pub fn reserve(&mut self, prefix: &str) -> &mut Self {
+        let prefix = prefix.trim_end_matches('/');
+        if !prefix.is_empty() && !self.reserved.iter().any(|p| p == prefix) {
+            self.reserved.push(prefix.to_string());
+        }
+        self
+    }
+
+    fn is_reserved(&self, path: &str) -> bool {
+        self.reserved.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+    }
+
+    /// 挂载全局中间件，包裹所有请求（含 404、405 与 OPTIONS），先挂载的在外层
+    ///
+    /// 全局中间件在路由匹配之前执行，改写请求的方法或路径会改变匹配到的路由。
+    pub fn layer(&mut self, middleware: impl Middleware) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// 处理请求：依次经过全局中间件、路由匹配、分组中间件，最后调用处理函数
+    pub async fn handle(self: Arc<Self>, ctx: Context) -> Response {
+        let chain = self.middleware.to_vec();
+        let router = Arc::clone(&self);
+        let dispatch = move |ctx: Context| Arc::clone(&router).dispatch(ctx);
+        Next::new(chain, Arc::new(dispatch)).run(ctx).await
+    }
+
+    /// 在全局中间件之内按当前的方法和路径匹配路由
+    async fn dispatch(self: Arc<Self>, mut ctx: Context) -> Response {
+        if ctx.request.method == Method::Options {
+            return self.options(&ctx.request.path);
+        }
+        match self.resolve(&ctx.request.method, &ctx.request.path) {
+            Resolution::Matched {
+                handler,
+                params,
+                middleware,
+                ..
+            } => {
+                ctx.params = params;
+                Next::new(middleware, handler).run(ctx).await
+            }
+            Resolution::MethodNotAllowed(allowed) => {
+                Response::error(StatusCode::MethodNotAllowed, "Method Not Allowed").header("Allow", join_methods(&allowed))
+            }
+            Resolution::NotFound => Response::error(StatusCode::NotFound, "Not Found"),
+        }
+    }
+
+    /// 响应 OPTIONS 请求，`OPTIONS *` 列出服务器支持的全部方法；
+    /// CORS 预检所需的响应头由 [`crate::middleware::Cors`] 补充
+    fn options(&self, path: &str) -> Response {
+        let allowed = if path == "*" {
+            [Method::Get, Method::Head, Method::Post, Method::Put, Method::Delete, Method::Patch, Method::Options].to_vec()
+        } else {
+            self.allowed_methods(path)
+        };
+        if allowed == [Method::Options] {
+            return Response::error(StatusCode::NotFound, "Not Found");
+        }
+        Response::new(StatusCode::NoContent).header("Allow", join_methods(&allowed))
+    }
+
+    /// 在共同前缀下注册一组路由，例如 `/api/v1`；前缀同时被保留，见 [`Router::reserve`]
+    pub fn group(&mut self, prefix: &str, build: impl FnOnce(&mut Group<'_>)) -> &mut Self {
+        self.reserve(prefix);
+        build(&mut Group {
+            router: self,
+            prefix: prefix.trim_end_matches('/').to_string(),
+            middleware: Vec::new(),
+        });
+        self
+    }
+
+    /// 查找处理函数；没有显式 HEAD 路由时按 GET 匹配，没有路由匹配且路径未被保留时交给兜底处理函数
+    ///
+    /// 兜底处理函数只接受 GET 和 HEAD，其它方法返回 405，与 OPTIONS 列出的方法一致。
+    pub fn resolve(&self, method: &Method, path: &str) -> Resolution {
+        let candidates: Vec<(&Route, Params)> = self
+            .routes
+            .iter()
+            .filter_map(|route| route.pattern.matches(path).map(|params| (route, params)))
+            .collect();
+
+        if candidates.is_empty() {
+            return match &self.fallback {
+                Some(_) if self.is_reserved(path) => Resolution::NotFound,
+                Some(handler) if matches!(method, Method::Get | Method::Head) => Resolution::Matched {
+                    handler: Arc::clone(handler),
+                    params: Params::default(),
+                    max_body_size: None,
+                    middleware: Vec::new(),
+                },
+                Some(_) => Resolution::MethodNotAllowed(vec![Method::Get, Method::Head, Method::Options]),
+                None => Resolution::NotFound,
+            };
+        }
+
+        let accepts = |route: &Route, method: &Method| route.methods.contains(method);
+        let best = |method: &Method| {
+            // 同样具体时先注册的路由优先
+            candidates
+                .iter()
+                .rev()
+                .filter(|(route, _)| accepts(route, method))
+                .max_by_key(|(route, _)| route.pattern.specificity())
+        };
+        let found = match method {
+            Method::Head => best(&Method::Head).or_else(|| best(&Method::Get)),
+            method => best(method),
+        };
+        match found {
+            Some((route, params)) => Resolution::Matched {
+                handler: Arc::clone(&route.handler),
+                params: params.clone(),
+                max_body_size: route.max_body_size,
+                middleware: route.middleware.clone(),
+            },
+            None => Resolution::MethodNotAllowed(Self::allowed(candidates.iter().map(|(route, _)| *route))),
+        }
+    }
+
+    /// 路径允许的方法；没有路由匹配时为兜底处理函数的 GET/HEAD
+    pub fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let routes: Vec<&Route> = self
+            .routes
+            .iter()
+            .filter(|route| route.pattern.matches(path).is_some())
+            .collect();
+        if routes.is_empty() && self.fallback.is_some() && !self.is_reserved(path) {
+            return vec![Method::Get, Method::Head, Method::Options];
+        }
+        Self::allowed(routes.into_iter())
+    }
+
+    /// 合并路由方法，允许 GET 时自动允许 HEAD，并总是允许 OPTIONS
+    fn allowed<'a>(routes: impl Iterator<Item = &'a Route>) -> Vec<Method> {
+        let mut methods: Vec<Method> = Vec::new();
+        for method in routes.flat_map(|route| route.methods.iter()) {
+            if !methods.contains(method) {
+                methods.push(method.clone());
+            }
+        }
+        if methods.contains(&Method::Get) && !methods.contains(&Method::Head) {
+            methods.push(Method::Head);
+        }
+        methods.push(Method::Options);
+        methods
+    }
+}
+
+impl Routes for Router {
+    fn route(&mut self, methods: &[Method], path: &str, handler: impl Handler) -> &mut Route {
+        let pattern = Pattern::parse(path).unwrap_or_else(|e| panic!("invalid route pattern `{}`: {}", path, e));
+        self.routes.push(Route {
+            methods: methods.to_vec(),
+            pattern,
+            handler: Arc::new(handler),
+            max_body_size: None,
+            middleware: Vec::new(),
+        });
+        self.routes.last_mut().expect("route was just pushed")
+    }
+}
+
+fn join_methods(methods: &[Method]) -> String {
+    methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", ")
+}
+
+/// 共享路径前缀的一组路由
+pub struct Group<'a> {
+    router: &'a mut Router,
+    prefix: String,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Group<'_> {
+    /// 为之后在本组（含嵌套分组）注册的路由挂载中间件，先挂载的在外层
+    ///
+    /// 分组中间件在路由匹配之后、全局中间件之内执行，只包裹匹配到本组路由的请求。
+    #[cfg_attr(not(test), expect(dead_code))]
+    pub fn layer(&mut self, middleware: impl Middleware) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// 嵌套分组，前缀叠加，继承外层分组已挂载的中间件
+    #[cfg_attr(not(test), expect(dead_code))]
+    pub fn group(&mut self, prefix: &str, build: impl FnOnce(&mut Group<'_>)) -> &mut Self {
+        build(&mut Group {
+            router: self.router,
+            prefix: format!("{}{}", self.prefix, prefix.trim_end_matches('/')),
+            middleware: self.middleware.clone(),
+        });
+        self
+    }
+}
+
+impl Routes for Group<'_> {
+    fn route(&mut self, methods: &[Method], path: &str, handler: impl Handler) -> &mut Route {
+        let path = format!("{}{}", self.prefix, path);
+        let middleware = self.middleware.clone();
+        let route = self.router.route(methods, &path, handler);
+        route.middleware = middleware;
+        route
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::{Headers, RequestLine, StatusCode};
+
+    /// 在响应头 `X-Route` 中标明自己名字的处理函数，`:id`、`*rest` 参数放进 `X-Params`
+    fn named(name: &'static str) -> impl Handler {
+        move |ctx: Context| async move {
+            let params = ["id", "rest"]
+                .iter()
+                .filter_map(|key| ctx.params.get(key).map(|v| format!("{}={}", key, v)))
+                .collect::<Vec<_>>()
+                .join("&");
+            Response::new(StatusCode::Ok).header("X-Route", name).header("X-Params", params)
+        }
+    }
+
+    fn context(method: &Method, path: &str, params: Params) -> Context {
+        let line = RequestLine::parse(&format!("{} {} HTTP/1.1", method, path)).unwrap();
+        Context {
+            request: line.into_request(Headers::new(), "127.0.0.1:1".parse().unwrap(), Vec::new()),
+            params,
+            config: Arc::new(Config::default()),
+        }
+    }
+
+    /// 匹配到的路由名及参数；没有匹配时为 `Err(允许的方法)`，404 时方法列表为空
+    async fn dispatch(router: &Router, method: Method, path: &str) -> Result<(String, String), Vec<Method>> {
+        match router.resolve(&method, path) {
+            Resolution::Matched { handler, params, .. } => {
+                let response = handler.call(context(&method, path, params)).await;
+                let header = |name| response.headers.get(name).unwrap_or("").to_string();
+                Ok((header("X-Route"), header("X-Params")))
+            }
+            Resolution::MethodNotAllowed(allowed) => Err(allowed),
+            Resolution::NotFound => Err(Vec::new()),
+        }
+    }
+
+    fn route(name: &str, params: &str) -> Result<(String, String), Vec<Method>> {
+        Ok((name.to_string(), params.to_string()))
+    }
+
+    #[tokio::test]
+    async fn distinguishes_not_found_from_method_not_allowed() {
+        let mut router = Router::new();
+        router.group("/api", |api| {
+            api.post("/login", named("login"));
+        });
+        router.fallback(named("static"));
+
+        assert_eq!(dispatch(&router, Method::Post, "/api/login").await, route("login", ""));
+        assert_eq!(
+            dispatch(&router, Method::Get, "/api/login").await,
+            Err(vec![Method::Post, Method::Options])
+        );
+        // 保留前缀下没有路由的路径不交给兜底处理函数
+        assert_eq!(dispatch(&router, Method::Get, "/api/unknown").await, Err(Vec::new()));
+        assert_eq!(dispatch(&router, Method::Post, "/api/unknown").await, Err(Vec::new()));
+
+        // 静态路径只接受 GET 和 HEAD
+        assert_eq!(dispatch(&router, Method::Get, "/index.html").await, route("static", ""));
+        assert_eq!(dispatch(&router, Method::Head, "/index.html").await, route("static", ""));
+        for method in [Method::Post, Method::Put, Method::Delete, Method::Trace] {
+            assert_eq!(
+                dispatch(&router, method, "/index.html").await,
+                Err(vec![Method::Get, Method::Head, Method::Options])
+            );
+        }
+        assert_eq!(
+            router.allowed_methods("/index.html"),
+            [Method::Get, Method::Head, Method::Options]
+        );
+
+        // 没有兜底处理函数时未知路径一律 404
+        let mut router = Router::new();
+        router.get("/status", named("status"));
+        assert_eq!(dispatch(&router, Method::Post, "/index.html").await, Err(Vec::new()));
+        assert_eq!(
+            dispatch(&router, Method::Delete, "/status").await,
+            Err(vec![Method::Get, Method::Head, Method::Options])
+        );
+    }
+
+    #[tokio::test]
+    async fn dispatches_by_method() {
+        let mut router = Router::new();
+        router.get("/items/:id", named("get"));
+        router.put("/items/:id", named("put"));
+        router.delete("/items/:id", named("delete"));
+        router.patch("/items/:id", named("patch"));
+        router.route(&[Method::Head], "/items/special", named("head"));
+
+        assert_eq!(dispatch(&router, Method::Get, "/items/1").await, route("get", "id=1"));
+        assert_eq!(dispatch(&router, Method::Put, "/items/1").await, route("put", "id=1"));
+        assert_eq!(dispatch(&router, Method::Delete, "/items/1").await, route("delete", "id=1"));
+        assert_eq!(dispatch(&router, Method::Patch, "/items/1").await, route("patch", "id=1"));
+        // 没有显式 HEAD 路由时按 GET 处理，有时优先使用
+        assert_eq!(dispatch(&router, Method::Head, "/items/1").await, route("get", "id=1"));
+        assert_eq!(dispatch(&router, Method::Head, "/items/special").await, route("head", ""));
+        assert_eq!(
+            dispatch(&router, Method::Post, "/items/1").await,
+            Err(vec![Method::Get, Method::Put, Method::Delete, Method::Patch, Method::Head, Method::Options])
+        );
+    }
+
+    #[tokio::test]
+    async fn extracts_params_and_wildcards() {
+        let mut router = Router::new();
+        router.get("/users/:id", named("user"));
+        router.get("/files/*rest", named("files"));
+
+        assert_eq!(dispatch(&router, Method::Get, "/users/42").await, route("user", "id=42"));
+        assert_eq!(dispatch(&router, Method::Get, "/users/42/").await, route("user", "id=42"));
+        // 参数只匹配单个非空路径段
+        assert_eq!(dispatch(&router, Method::Get, "/users/").await, Err(Vec::new()));
+        assert_eq!(dispatch(&router, Method::Get, "/users/42/posts").await, Err(Vec::new()));
+
+        // 通配段匹配剩余的全部路径，包括空路径
+        assert_eq!(dispatch(&router, Method::Get, "/files/a/b/c.txt").await, route("files", "rest=a/b/c.txt"));
+        assert_eq!(dispatch(&router, Method::Get, "/files/").await, route("files", "rest="));
+        assert_eq!(dispatch(&router, Method::Get, "/filesystem").await, Err(Vec::new()));
+    }
+
+    #[tokio::test]
+    async fn prefers_more_specific_patterns() {
+        let mut router = Router::new();
+        router.get("/users/*rest", named("wildcard"));
+        router.get("/users/:id", named("param"));
+        router.get("/users/me", named("static"));
+        router.get("/users/:id/posts", named("posts"));
+        router.get("/users/:id", named("duplicate"));
+
+        assert_eq!(dispatch(&router, Method::Get, "/users/me").await, route("static", ""));
+        // 同样具体时先注册的路由优先
+        assert_eq!(dispatch(&router, Method::Get, "/users/7").await, route("param", "id=7"));
+        assert_eq!(dispatch(&router, Method::Get, "/users/7/posts").await, route("posts", "id=7"));
+        assert_eq!(dispatch(&router, Method::Get, "/users/7/likes").await, route("wildcard", "rest=7/likes"));
+    }
+
+    #[tokio::test]
+    async fn nests_group_prefixes() {
+        let mut router = Router::new();
+        router.group("/api/", |api| {
+            api.group("/v1", |v1| {
+                v1.get("/users/:id", named("v1"));
+            });
+            api.get("/health", named("health"));
+        });
+
+        assert_eq!(dispatch(&router, Method::Get, "/api/v1/users/3").await, route("v1", "id=3"));
+        assert_eq!(dispatch(&router, Method::Get, "/api/health").await, route("health", ""));
+        assert_eq!(dispatch(&router, Method::Get, "/v1/users/3").await, Err(Vec::new()));
+    }
+
+    /// 进入时把名字追加到请求头 `X-Before`，返回时追加到响应头 `X-After`
+    fn trace(label: &'static str) -> impl Middleware {
+        move |mut ctx: Context, next: Next| async move {
+            ctx.request.headers.append("x-before", label);
+            let mut response = next.run(ctx).await;
+            response.headers.append("X-After", label);
+            response
+        }
+    }
+
+    /// 把请求头 `X-Before` 原样放进响应，显示请求经过中间件的顺序
+    async fn echo_trace(ctx: Context) -> Response {
+        let before = ctx.request.headers.get("x-before").unwrap_or("").to_string();
+        Response::new(StatusCode::Ok).header("X-Before", before)
+    }
+
+    /// 缺少 `Authorization` 时不调用处理函数，直接拒绝
+    async fn require_auth(ctx: Context, next: Next) -> Response {
+        if !ctx.request.headers.contains("authorization") {
+            return Response::error(StatusCode::Forbidden, "Forbidden");
+        }
+        next.run(ctx).await
+    }
+
+    async fn handle(router: &Arc<Router>, method: Method, path: &str, headers: &[(&str, &str)]) -> Response {
+        let mut ctx = context(&method, path, Params::default());
+        for (name, value) in headers {
+            ctx.request.headers.append(name, value);
+        }
+        Arc::clone(router).handle(ctx).await
+    }
+
+    fn after(response: &Response) -> Vec<&str> {
+        response.headers.iter().filter(|(k, _)| *k == "X-After").map(|(_, v)| v).collect()
+    }
+
+    #[tokio::test]
+    async fn runs_global_then_group_middleware_in_order() {
+        let mut router = Router::new();
+        router.layer(trace("global-1")).layer(trace("global-2"));
+        router.group("/api", |api| {
+            api.layer(trace("api"));
+            api.group("/admin", |admin| {
+                admin.layer(require_auth).layer(trace("admin"));
+                admin.get("/stats", echo_trace);
+            });
+            api.get("/public", echo_trace);
+        });
+        let router = Arc::new(router);
+
+        // 先挂载的在外层：请求由外向内，响应由内向外
+        let response = handle(&router, Method::Get, "/api/public", &[]).await;
+        assert_eq!(response.headers.get("X-Before"), Some("global-1, global-2, api"));
+        assert_eq!(after(&response), ["api", "global-2", "global-1"]);
+
+        let response = handle(&router, Method::Get, "/api/admin/stats", &[("authorization", "token")]).await;
+        assert_eq!(response.status, StatusCode::Ok);
+        assert_eq!(response.headers.get("X-Before"), Some("global-1, global-2, api, admin"));
+        assert_eq!(after(&response), ["admin", "api", "global-2", "global-1"]);
+
+        // 中途返回的响应不经过内层中间件和处理函数，但仍经过外层
+        let response = handle(&router, Method::Get, "/api/admin/stats", &[]).await;
+        assert_eq!(response.status, StatusCode::Forbidden);
+        assert_eq!(response.headers.get("X-Before"), None);
+        assert_eq!(after(&response), ["api", "global-2", "global-1"]);
+
+        // 没有匹配的路由时只经过全局中间件
+        let response = handle(&router, Method::Get, "/api/missing", &[]).await;
+        assert_eq!(response.status, StatusCode::NotFound);
+        assert_eq!(after(&response), ["global-2", "global-1"]);
+    }
+
+    #[tokio::test]
+    async fn global_middleware_can_rewrite_the_route() {
+        let mut router = Router::new();
+        router.layer(|mut ctx: Context, next: Next| async move {
+            if let Some(rest) = ctx.request.path.strip_prefix("/v0/") {
+                ctx.request.path = format!("/api/{}", rest);
+            }
+            // 表单只能发送 POST，由 `_method` 查询参数指定实际方法
+            if let Some((_, method)) = ctx.request.query.iter().find(|(k, _)| k == "_method")
+                && let Some(method) = Method::parse(method)
+            {
+                ctx.request.method = method;
+            }
+            next.run(ctx).await
+        });
+        router.get("/api/users/:id", named("get"));
+        router.delete("/api/users/:id", named("delete"));
+        let router = Arc::new(router);
+
+        let route = |response: Response| response.headers.get("X-Route").map(str::to_string);
+        assert_eq!(route(handle(&router, Method::Get, "/v0/users/1", &[]).await).as_deref(), Some("get"));
+        let response = handle(&router, Method::Post, "/api/users/1?_method=DELETE", &[]).await;
+        assert_eq!(response.headers.get("X-Params"), Some("id=1"));
+        assert_eq!(route(response).as_deref(), Some("delete"));
+        let response = handle(&router, Method::Post, "/api/users/1", &[]).await;
+        assert_eq!(response.status, StatusCode::MethodNotAllowed);
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(Pattern::parse("users").is_err());
+        assert!(Pattern::parse("/users/:").is_err());
+        assert!(Pattern::parse("/files/*rest/more").is_err());
+        assert!(Pattern::parse("/").is_ok());
+    }
+}
@@ -1,32 +1,168 @@
+use crate::config::{Config, ConfigSource};
+use crate::site::VirtualHosts;
+use crate::tls::CertificateStore;
 use std::io::Result;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
 
 pub struct Server {
-    listener: TcpListener,
+    listeners: Vec<TcpListener>,
+    tls_listeners: Vec<TcpListener>,
+    /// 启用 HTTPS 时的证书存储和证书检查间隔
+    tls: Option<(Arc<CertificateStore>, Duration)>,
+    hosts: Arc<RwLock<Arc<VirtualHosts>>>,
+    source: ConfigSource,
 }
 
 impl Server {
-    pub async fn new(address: &str) -> Result<Self> {
-        let listener = TcpListener::bind(address).await?;
-        let addr = listener.local_addr()?;
-        println!("Server is starting, listening on {}", addr);
-        Ok(Self { listener })
+    pub async fn new(config: Config, source: ConfigSource) -> Result<Self> {
+        let mut listeners = Vec::with_capacity(config.server.listen.len());
+        for address in &config.server.listen {
+            let listener = TcpListener::bind(address).await?;
+            let addr = listener.local_addr()?;
+            println!("Server is starting, listening on {}", addr);
+            listeners.push(listener);
+        }
+        let mut tls_listeners = Vec::new();
+        let mut tls = None;
+        if let Some(tls_config) = &config.tls {
+            let store = CertificateStore::new(&config)?;
+            for address in &tls_config.listen {
+                let listener = TcpListener::bind(address).await?;
+                println!("Listening for HTTPS on {}", listener.local_addr()?);
+                tls_listeners.push(listener);
+            }
+            tls = Some((store, Duration::from_secs(tls_config.check_interval)));
+        }
+        println!("Serving static files from {}", config.server.root.display());
+        let mut hosts: Vec<_> = config.hosts.iter().collect();
+        hosts.sort_by_key(|(name, _)| name.as_str());
+        for (name, host) in hosts {
+            println!("Serving {} from {}", name, host.root.display());
+        }
+        Ok(Self {
+            listeners,
+            tls_listeners,
+            tls,
+            hosts: Arc::new(RwLock::new(Arc::new(VirtualHosts::new(config)))),
+            source,
+        })
     }
 
-    pub async fn run(&self) -> Result<()> {
-        loop {
-            match self.listener.accept().await {
-                Ok((stream, addr)) => {
-                    tokio::spawn(async move {
-                        if let Err(e) = crate::http::handle_connection(stream, addr).await {
-                            eprintln!("Error handling connection: {}", e);
-                        }
-                    });
+    pub async fn run(self) -> Result<()> {
+        for listener in self.listeners {
+            let hosts = Arc::clone(&self.hosts);
+            tokio::spawn(accept_loop(listener, None, hosts));
+        }
+        let store = match self.tls {
+            Some((store, interval)) => {
+                let acceptor = store.acceptor()?;
+                for listener in self.tls_listeners {
+                    let hosts = Arc::clone(&self.hosts);
+                    tokio::spawn(accept_loop(listener, Some(acceptor.clone()), hosts));
                 }
-                Err(e) => {
-                    eprintln!("Error accepting connection: {}", e);
+                if !interval.is_zero() {
+                    tokio::spawn(Arc::clone(&store).watch(interval));
                 }
+                Some(store)
+            }
+            None => None,
+        };
+        reload_on_hangup(self.hosts, store, self.source).await
+    }
+}
+
+/// 接受连接；`tls` 不为空时先完成 TLS 握手
+async fn accept_loop(listener: TcpListener, tls: Option<TlsAcceptor>, hosts: Arc<RwLock<Arc<VirtualHosts>>>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                crate::utils::debug(&format!("Accepted connection from {}", addr));
+                // 每个连接持有接受时的配置快照，重新加载不影响进行中的请求
+                let hosts = current(&hosts);
+                let tls = tls.clone();
+                tokio::spawn(async move {
+                    let result = match tls {
+                        None => crate::http::handle_connection(stream, addr, hosts).await,
+                        Some(acceptor) => {
+                            match tokio::time::timeout(crate::tls::HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                                Ok(Ok(stream)) => crate::http::handle_connection(stream, addr, hosts).await,
+                                Ok(Err(e)) => {
+                                    crate::utils::debug(&format!("TLS handshake with {} failed: {}", addr, e));
+                                    Ok(())
+                                }
+                                Err(_) => {
+                                    crate::utils::debug(&format!("TLS handshake with {} timed out", addr));
+                                    Ok(())
+                                }
+                            }
+                        }
+                    };
+                    if let Err(e) = result {
+                        eprintln!("Error handling connection: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                eprintln!("Error accepting connection: {}", e);
             }
         }
     }
 }
+
+fn current(hosts: &RwLock<Arc<VirtualHosts>>) -> Arc<VirtualHosts> {
+    Arc::clone(&hosts.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// 收到 SIGHUP 时重新读取配置和证书，校验失败则保留旧配置
+#[cfg(unix)]
+async fn reload_on_hangup(
+    hosts: Arc<RwLock<Arc<VirtualHosts>>>,
+    tls: Option<Arc<CertificateStore>>,
+    source: ConfigSource,
+) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        let new_config = match source.load() {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("Configuration reload failed, keeping previous configuration: {}", e);
+                continue;
+            }
+        };
+        let old_config = Arc::clone(&current(&hosts).default_site().config);
+        if new_config.server.listen != old_config.server.listen {
+            eprintln!("Changes to server.listen take effect after a restart");
+        }
+        if new_config.server.workers != old_config.server.workers {
+            eprintln!("Changes to server.workers take effect after a restart");
+        }
+        let tls_listen = |config: &Config| config.tls.as_ref().map(|tls| tls.listen.clone());
+        if tls_listen(&new_config) != tls_listen(&old_config) {
+            eprintln!("Changes to tls.listen take effect after a restart");
+        }
+        if let Some(store) = &tls
+            && new_config.tls.is_some()
+            && let Err(e) = store.reload(&new_config)
+        {
+            eprintln!("TLS certificate reload failed, keeping previous certificates: {}", e);
+        }
+        crate::utils::init_logging(new_config.log_settings());
+        *hosts.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(VirtualHosts::new(new_config));
+        println!("Configuration reloaded");
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn reload_on_hangup(
+    _hosts: Arc<RwLock<Arc<VirtualHosts>>>,
+    _tls: Option<Arc<CertificateStore>>,
+    _source: ConfigSource,
+) -> Result<()> {
+    std::future::pending().await
+}
@@ -0,0 +1,107 @@
+use crate::config::Config;
+use crate::errors::ErrorPages;
+use crate::router::Router;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 一份配置及据此构建的路由表和错误页，配置重新加载时整体替换
+pub struct Site {
+    pub config: Arc<Config>,
+    pub router: Arc<Router>,
+    pub errors: ErrorPages,
+}
+
+impl Site {
+    pub fn new(config: Config) -> Self {
+        let router = Arc::new(crate::handlers::router(&config));
+        let errors = ErrorPages::load(&config);
+        Self {
+            config: Arc::new(config),
+            router,
+            errors,
+        }
+    }
+}
+
+/// 按 `Host` 请求头选择站点：先查精确主机名，再查最长的通配符，都不匹配时使用默认站点
+pub struct VirtualHosts {
+    /// 由顶层配置构建，连接级别的设置（超时、请求数上限）也取自它
+    default: Arc<Site>,
+    exact: HashMap<String, Arc<Site>>,
+    /// 通配符去掉 `*` 后的后缀，如 `.example.com`，按长度降序排列
+    wildcards: Vec<(String, Arc<Site>)>,
+}
+
+impl VirtualHosts {
+    pub fn new(config: Config) -> Self {
+        let mut exact = HashMap::new();
+        let mut wildcards = Vec::new();
+        for (pattern, host) in &config.hosts {
+            let site = Arc::new(Site::new(config.for_host(host)));
+            match pattern.strip_prefix('*') {
+                Some(suffix) => wildcards.push((suffix.to_string(), site)),
+                None => {
+                    exact.insert(pattern.clone(), site);
+                }
+            }
+        }
+        wildcards.sort_by_key(|(suffix, _)| std::cmp::Reverse(suffix.len()));
+        Self {
+            default: Arc::new(Site::new(config)),
+            exact,
+            wildcards,
+        }
+    }
+
+    pub fn default_site(&self) -> &Arc<Site> {
+        &self.default
+    }
+
+    /// `host` 为 [`host_name`] 规范化后的主机名
+    pub fn select(&self, host: Option<&str>) -> &Arc<Site> {
+        let Some(host) = host else {
+            return &self.default;
+        };
+        if let Some(site) = self.exact.get(host) {
+            return site;
+        }
+        self.wildcards
+            .iter()
+            .find(|(suffix, _)| host.ends_with(suffix.as_str()))
+            .map_or(&self.default, |(_, site)| site)
+    }
+}
+
+/// 从 `Host` 请求头中取出小写的主机名，去掉端口和末尾的点
+///
+/// 格式错误时返回 `None`；重复的 `Host` 头合并后含有逗号，同样视为错误。
+pub fn host_name(value: &str) -> Option<String> {
+    let value = value.trim();
+    let name = if let Some(rest) = value.strip_prefix('[') {
+        // IPv6 字面量，如 `[::1]:8080`
+        let (address, port) = rest.split_once(']')?;
+        if !address.bytes().all(|b| b.is_ascii_hexdigit() || b == b':' || b == b'.') {
+            return None;
+        }
+        if !port.is_empty() && !port.strip_prefix(':').is_some_and(is_port) {
+            return None;
+        }
+        return Some(format!("[{}]", address.to_ascii_lowercase()));
+    } else {
+        match value.rsplit_once(':') {
+            Some((name, port)) if is_port(port) => name,
+            Some(_) => return None,
+            None => value,
+        }
+    };
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_'));
+    valid.then(|| name.to_ascii_lowercase())
+}
+
+fn is_port(port: &str) -> bool {
+    !port.is_empty() && port.len() <= 5 && port.bytes().all(|b| b.is_ascii_digit())
+}
@@ -0,0 +1,212 @@
+use crate::config::Config;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::crypto::{CryptoProvider, ring};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::{ServerConfig, version};
+
+/// 服务器只实现 HTTP/1.x，ALPN 协商时不会选择 h2
+const ALPN_PROTOCOLS: [&[u8]; 2] = [b"http/1.1", b"http/1.0"];
+
+/// TLS 握手的最长时间，防止半开连接长期占用
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 一份证书文件及其对应的主机名模式，`None` 表示默认证书
+#[derive(Debug, Clone)]
+struct Source {
+    pattern: Option<String>,
+    cert: PathBuf,
+    key: PathBuf,
+}
+
+/// 按 SNI 选择的一组证书，匹配规则与虚拟主机相同
+#[derive(Debug)]
+struct Certificates {
+    default: Arc<CertifiedKey>,
+    exact: HashMap<String, Arc<CertifiedKey>>,
+    /// 通配符去掉 `*` 后的后缀，按长度降序排列
+    wildcards: Vec<(String, Arc<CertifiedKey>)>,
+    sources: Vec<Source>,
+    /// 读取时各文件的修改时间，用于发现证书更新
+    modified: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Certificates {
+    fn load(sources: Vec<Source>, provider: &CryptoProvider) -> Result<Self> {
+        let mut default = None;
+        let mut exact = HashMap::new();
+        let mut wildcards = Vec::new();
+        let mut modified = Vec::new();
+        for source in &sources {
+            // 先记录修改时间再读取，读取期间发生的更新会在下一次检查时发现
+            for path in [&source.cert, &source.key] {
+                modified.push((path.clone(), modified_time(path)));
+            }
+            let key = Arc::new(load_certified_key(&source.cert, &source.key, provider)?);
+            match source.pattern.as_deref() {
+                None => default = Some(key),
+                Some(pattern) => match pattern.strip_prefix('*') {
+                    Some(suffix) => wildcards.push((suffix.to_string(), key)),
+                    None => {
+                        exact.insert(pattern.to_string(), key);
+                    }
+                },
+            }
+        }
+        wildcards.sort_by_key(|(suffix, _): &(String, _)| std::cmp::Reverse(suffix.len()));
+        let default = default.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "no default certificate"))?;
+        Ok(Self {
+            default,
+            exact,
+            wildcards,
+            sources,
+            modified,
+        })
+    }
+
+    /// 客户端未发送 SNI 或没有匹配的主机时使用默认证书
+    fn select(&self, server_name: Option<&str>) -> Arc<CertifiedKey> {
+        let Some(name) = server_name.map(str::to_ascii_lowercase) else {
+            return Arc::clone(&self.default);
+        };
+        let name = name.trim_end_matches('.');
+        if let Some(key) = self.exact.get(name) {
+            return Arc::clone(key);
+        }
+        self.wildcards
+            .iter()
+            .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+            .map_or_else(|| Arc::clone(&self.default), |(_, key)| Arc::clone(key))
+    }
+
+    /// 各证书文件当前的修改时间，与读取时的记录不同说明文件已更新
+    fn modified_now(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        self.modified
+            .iter()
+            .map(|(path, _)| (path.clone(), modified_time(path)))
+            .collect()
+    }
+}
+
+/// 证书来源：顶层 `[tls]` 的默认证书以及各虚拟主机的证书
+fn sources(config: &Config) -> Result<Vec<Source>> {
+    let tls = config
+        .tls
+        .as_ref()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "TLS is not configured"))?;
+    let mut sources = vec![Source {
+        pattern: None,
+        cert: tls.cert.clone(),
+        key: tls.key.clone(),
+    }];
+    for (pattern, host) in &config.hosts {
+        if let Some(certificate) = &host.tls {
+            sources.push(Source {
+                pattern: Some(pattern.clone()),
+                cert: certificate.cert.clone(),
+                key: certificate.key.clone(),
+            });
+        }
+    }
+    Ok(sources)
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// 读取 PEM 证书链和私钥，并确认两者匹配
+fn load_certified_key(cert: &Path, key: &Path, provider: &CryptoProvider) -> Result<CertifiedKey> {
+    let invalid = |path: &Path, message: String| {
+        Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), message))
+    };
+    let pem = std::fs::read(cert).map_err(|e| invalid(cert, e.to_string()))?;
+    let chain = CertificateDer::pem_slice_iter(&pem)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| invalid(cert, e.to_string()))?;
+    if chain.is_empty() {
+        return Err(invalid(cert, "no certificates found".to_string()));
+    }
+    let pem = std::fs::read(key).map_err(|e| invalid(key, e.to_string()))?;
+    let private_key = PrivateKeyDer::from_pem_slice(&pem).map_err(|e| invalid(key, e.to_string()))?;
+    CertifiedKey::from_der(chain, private_key, provider).map_err(|e| invalid(key, e.to_string()))
+}
+
+/// 按 SNI 提供证书，证书可在运行中整体替换，不影响已建立的连接
+#[derive(Debug)]
+pub struct CertificateStore {
+    current: RwLock<Arc<Certificates>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl ResolvesServerCert for CertificateStore {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current().select(client_hello.server_name()))
+    }
+}
+
+impl CertificateStore {
+    pub fn new(config: &Config) -> Result<Arc<Self>> {
+        let provider = Arc::new(ring::default_provider());
+        let certificates = Certificates::load(sources(config)?, &provider)?;
+        Ok(Arc::new(Self {
+            current: RwLock::new(Arc::new(certificates)),
+            provider,
+        }))
+    }
+
+    /// 支持 TLS 1.2 与 1.3 的接受器，证书由本存储按 SNI 提供
+    pub fn acceptor(self: &Arc<Self>) -> Result<TlsAcceptor> {
+        let mut config = ServerConfig::builder_with_provider(Arc::clone(&self.provider))
+            .with_protocol_versions(&[&version::TLS13, &version::TLS12])
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::clone(self) as Arc<dyn ResolvesServerCert>);
+        config.alpn_protocols = ALPN_PROTOCOLS.iter().map(|p| p.to_vec()).collect();
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    /// 按新配置重新读取所有证书；失败时保留原有证书
+    pub fn reload(&self, config: &Config) -> Result<()> {
+        let certificates = Certificates::load(sources(config)?, &self.provider)?;
+        *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(certificates);
+        Ok(())
+    }
+
+    fn current(&self) -> Arc<Certificates> {
+        Arc::clone(&self.current.read().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    /// 定期检查证书文件，发现更新后重新读取，例如证书自动续期之后
+    pub async fn watch(self: Arc<Self>, interval: Duration) {
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        // 读取失败的文件状态，文件再次变化前不重复尝试和报错
+        let mut failed = None;
+        loop {
+            ticks.tick().await;
+            let current = self.current();
+            let modified = current.modified_now();
+            if modified == current.modified || failed.as_ref() == Some(&modified) {
+                continue;
+            }
+            match Certificates::load(current.sources.clone(), &self.provider) {
+                Ok(certificates) => {
+                    *self.current.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(certificates);
+                    failed = None;
+                    println!("TLS certificates reloaded");
+                }
+                Err(e) => {
+                    eprintln!("TLS certificate reload failed, keeping previous certificates: {}", e);
+                    failed = Some(modified);
+                }
+            }
+        }
+    }
+}
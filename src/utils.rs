@@ -2,10 +2,53 @@ use chrono::Utc;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
 use std::time::Instant;
-use tokio::net::TcpStream;
-use tokio::io::AsyncWriteExt;
 
+/// 日志配置
+#[derive(Debug, Clone)]
+pub struct LogSettings {
+    /// 访问日志文件路径
+    pub file: PathBuf,
+    /// 控制台日志级别：0 为静默，1 为访问日志，2 及以上包含调试信息
+    pub verbosity: u8,
+}
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::from("access.log"),
+            verbosity: 1,
+        }
+    }
+}
+static LOG_SETTINGS: RwLock<Option<LogSettings>> = RwLock::new(None);
+/// 设置全局日志配置
+pub fn init_logging(settings: LogSettings) {
+    *LOG_SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = Some(settings);
+}
+/// 读取当前日志配置
+fn log_settings() -> LogSettings {
+    LOG_SETTINGS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+/// 输出调试信息（仅在详细模式下）
+pub fn debug(message: &str) {
+    if log_settings().verbosity >= 2 {
+        eprintln!("{}", message);
+    }
+}
+/// 将路径开头的 `~` 展开为当前用户的主目录
+pub fn expand_home(path: &Path) -> PathBuf {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    match (path.strip_prefix("~"), home) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
 /// 日志条目，记录HTTP请求信息
 #[derive(Debug)]
 pub struct LogEntry {
@@ -13,6 +56,8 @@ pub struct LogEntry {
     path: String,
     client_addr: Option<SocketAddr>,
     start_time: Instant,
+    /// 虚拟主机单独的日志文件，缺省使用全局设置
+    file: Option<PathBuf>,
 }
 impl LogEntry {
     /// 创建新的日志条目
@@ -22,22 +67,32 @@ impl LogEntry {
             path,
             client_addr,
             start_time: Instant::now(),
+            file: None,
         }
     }
-    /// 记录日志到控制台和文件
-    pub fn log(&self, status_code: &str) {
-        let log_message = self.format_log_message(status_code);
+    /// 写入指定的日志文件而不是全局设置中的文件
+    pub fn with_file(mut self, file: &Path) -> Self {
+        self.file = Some(file.to_path_buf());
+        self
+    }
+    /// 记录日志到控制台和文件，`bytes_sent` 为写出的响应字节数（含头部）
+    pub fn log(&self, status_code: &str, bytes_sent: u64) {
+        let settings = log_settings();
+        let log_message = self.format_log_message(status_code, bytes_sent);
         
         // 输出到控制台
-        eprintln!("{}", log_message);
+        if settings.verbosity >= 1 {
+            eprintln!("{}", log_message);
+        }
         
         // 写入日志文件
-        if let Err(e) = self.write_to_file(&log_message) {
+        let file = self.file.as_ref().unwrap_or(&settings.file);
+        if let Err(e) = self.write_to_file(file, &log_message) {
             eprintln!("Failed to write log to file: {}", e);
         }
     }
     /// 格式化日志消息
-    fn format_log_message(&self, status_code: &str) -> String {
+    fn format_log_message(&self, status_code: &str, bytes_sent: u64) -> String {
         let elapsed = self.start_time.elapsed().as_millis();
         let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S");
         let client_info = self
@@ -46,70 +101,51 @@ impl LogEntry {
             .unwrap_or_else(|| "unknown".to_string());
 
         format!(
-            "[{}] \"{} {}\" {} {}ms - {}",
-            timestamp, self.method, self.path, status_code, elapsed, client_info
+            "[{}] \"{} {}\" {} {} {}ms - {}",
+            timestamp, self.method, self.path, status_code, bytes_sent, elapsed, client_info
         )
     }
     /// 将日志消息写入文件
-    fn write_to_file(&self, message: &str) -> std::io::Result<()> {
+    fn write_to_file(&self, path: &Path, message: &str) -> std::io::Result<()> {
         let mut file = OpenOptions::new()
             .create(true)
             .append(true)
-            .open("access.log")?;
+            .open(path)?;
         writeln!(file, "{}", message)
     }
 }
-/// 发送HTTP响应
-pub async fn send_response(
-    stream: &mut TcpStream,
-    status: &str,
-    body: &[u8],
-    content_type: &str,
-    extra_headers: Option<&str>,
-) -> std::io::Result<()> {
-    let mut response = format!(
-        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n",
-        status,
-        content_type,
-        body.len()
-    );
-    if let Some(headers) = extra_headers {
-        response.push_str(headers);
-        response.push_str("\r\n");
+/// 将请求路径解析为静态文件根目录下的真实路径
+///
+/// 拒绝 NUL、反斜杠和 `..` 路径段；解析符号链接后的结果仍须位于根目录之内。
+/// 文件不存在时返回 `NotFound`，越出根目录时返回 `PermissionDenied`，
+/// 非法路径返回 `InvalidInput`。所有读取静态文件的代码都必须经过这里。
+pub async fn resolve_safe_path(root: &Path, request_path: &str) -> std::io::Result<PathBuf> {
+    let invalid = |message| std::io::Error::new(std::io::ErrorKind::InvalidInput, message);
+    if request_path.contains(['\0', '\\']) {
+        return Err(invalid("path contains NUL or backslash"));
     }
-    response.push_str("\r\n");
-    stream.write_all(response.as_bytes()).await?;
-    stream.write_all(body).await?;
-    stream.flush().await?;
-    Ok(())
-}
-pub async fn send_400_response(stream: &mut TcpStream, message: &[u8]) -> std::io::Result<()> {
-    send_response(
-        stream,
-        "400 Bad Request",
-        message,
-        "text/plain",
-        Some("Cache-Control: no-store"),
-    ).await
-}
-pub async fn send_405_response(stream: &mut TcpStream, message: &[u8]) -> std::io::Result<()> {
-    send_response(
-        stream,
-        "405 Method Not Allowed",
-        message,
-        "text/plain",
-        Some("Cache-Control: no-store"),
-    ).await
-}
-pub async fn send_500_response(stream: &mut TcpStream, message: &[u8]) -> std::io::Result<()> {
-    send_response(
-        stream,
-        "500 Internal Server Error",
-        message,
-        "text/plain",
-        Some("Cache-Control: no-store"),
-    ).await
+
+    let root = tokio::fs::canonicalize(root).await?;
+    let mut path = root.clone();
+    for segment in request_path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return Err(invalid("path contains a parent directory segment")),
+            segment => path.push(segment),
+        }
+    }
+
+    // 符号链接可能指向根目录之外，必须比较解析后的真实路径
+    let resolved = tokio::fs::canonicalize(&path).await?;
+    if !resolved.starts_with(&root) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "path escapes the document root",
+        ));
+    }
+    Ok(resolved)
 }
+
 /*
 pub fn send_json_response(
     stream: &mut TcpStream,
@@ -153,52 +189,6 @@ pub fn get_content_type(path: &str) -> &'static str {
         _ => "application/octet-stream",
     }
 }
-pub fn resolve_safe_path(base: &str, request_path: &str) -> Result<std::path::PathBuf> {
-    let base_path = Path::new(base).canonicalize()?;
-    let clean_path = if request_path == "/" {
-        "index.html"
-    } else {
-        request_path.trim_start_matches('/')
-    };
-    let mut final_path = base_path.clone();
-    for component in Path::new(clean_path).components() {
-        match component {
-            Component::Normal(os_str) => {
-                final_path.push(os_str);
-            }
-            Component::ParentDir | Component::CurDir => {
-                continue;
-            }
-            Component::RootDir => {
-                continue;
-            }
-            _ => {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    "Invalid path component",
-                ));
-            }
-        }
-    }
-    if !final_path.starts_with(&base_path) {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidInput,
-            "Path traversal detected",
-        ));
-    }
-    Ok(final_path)
-}
-pub fn get_cache_control(path: &Path) -> &'static str {
-    let ext = path.extension().and_then(|s| s.to_str());
-    match ext {
-        Some("html") | Some("htm") => "no-cache, no-store, must-revalidate",
-        Some("css") | Some("js") | Some("png") | Some("jpg") | Some("jpeg") | Some("gif")
-        | Some("webp") | Some("ico") | Some("svg") | Some("woff") | Some("woff2") | Some("ttf") => {
-            "public, max-age=3600"
-        }
-        _ => "no-cache",
-    }
-}
 pub async fn serve_static_file(stream: &mut TcpStream, request_path: &str) -> Result<String> {
     let file_path = resolve_safe_path("public", request_path)?;
 
@@ -270,122 +260,108 @@ pub fn serve_500(stream: &mut TcpStream) -> Result<()> {
         Some("Cache-Control: no-cache"),
     )
 }
-fn serve_directory_listing(
-    stream: &mut TcpStream,
-    dir_path: &Path,
-    request_path: &str,
-) -> Result<()> {
-    let mut entries: Vec<(String, bool, Option<u64>, Option<SystemTime>)> = vec![];
-    // 读取目录内容
-    for entry in std::fs::read_dir(dir_path)? {
-        let entry = entry?;
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().into_owned();
-        let metadata = match std::fs::metadata(&path) {
-            Ok(meta) => meta,
-            Err(_) => continue, // 跳过无法获取元数据的项
-        };
-
-        let is_dir = metadata.is_dir();
-        let size = if is_dir { None } else { Some(metadata.len()) };
-        let modified = metadata.modified().ok();
+*/
+#[cfg(test)]
+mod tests {
+    use super::resolve_safe_path;
+    use crate::http::RequestLine;
+    use std::fs;
+    use std::io::ErrorKind;
+    use tempfile::TempDir;
 
-        entries.push((name, is_dir, size, modified));
-    }
-    // 排序：目录在前，文件在后；同类型按名称排序
-    entries.sort_by(|a, b| {
-        match (a.1, b.1) {
-            (true, false) => std::cmp::Ordering::Less, // 目录优先
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.0.cmp(&b.0), // 同类型按名称
+    /// 构造测试目录：`root/` 为文档根目录，`secret.txt` 位于根目录之外
+    fn fixture() -> (TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("index.html"), "index").unwrap();
+        fs::write(root.join("sub/page.html"), "page").unwrap();
+        fs::write(dir.path().join("secret.txt"), "secret").unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("secret.txt"), root.join("escape.txt")).unwrap();
+            std::os::unix::fs::symlink(dir.path(), root.join("escape-dir")).unwrap();
+            std::os::unix::fs::symlink(root.join("sub/page.html"), root.join("alias.html")).unwrap();
         }
-    });
-    // 构建 HTML
-    let mut html = String::from("<!DOCTYPE html><html><head>");
-    html.push_str(r#"<meta charset="utf-8"><title>Index of "#);
-    html.push_str(request_path);
-    html.push_str(r#"</title><style>
-        body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; margin: 2rem; }
-        h1 { color: #333; }
-        table { width: 100%; border-collapse: collapse; margin-top: 1rem; }
-        th, td { padding: 0.5rem 1rem; text-align: left; border-bottom: 1px solid #eee; }
-        th { font-weight: 600; color: #555; }
-        tr:hover { background-color: #f9f9f9; }
-        .dir::before { content: "📁 "; }
-        .file::before { content: "📄 "; }
-        .size { color: #888; font-size: 0.9em; }
-        a { text-decoration: none; color: #0070f3; }
-        a:hover { text-decoration: underline; }
-    </style></head><body>"#);
+        (dir, root)
+    }
 
-    html.push_str("<h1>Index of ");
-    html.push_str(request_path);
-    html.push_str(
-        "</h1><table><thead><tr><th>Name</th><th>Size</th><th>Modified</th></tr></thead><tbody>",
-    );
-    // 添加 ".." 返回上级（除非是根目录）
-    if request_path != "/" && request_path != "" {
-        let parent_path = Path::new(request_path)
-            .parent()
-            .map(|p| p.to_str().unwrap_or("/"))
-            .unwrap_or("/");
-        let display_parent = if parent_path.is_empty() {
-            "/"
-        } else {
-            parent_path
-        };
-        html.push_str(&format!(
-            r#"<tr><td><a href="{}" class="dir">../</a></td><td>-</td><td>-</td></tr>"#,
-            display_parent
-        ));
+    /// 按请求行解码路径后再解析，与实际请求走同一条路径
+    async fn resolve(root: &std::path::Path, target: &str) -> std::io::Result<std::path::PathBuf> {
+        let line = RequestLine::parse(&format!("GET {} HTTP/1.1", target))
+            .ok_or_else(|| std::io::Error::new(ErrorKind::InvalidInput, "rejected by request parser"))?;
+        resolve_safe_path(root, &line.path).await
     }
-    // 添加每个条目
-    for (name, is_dir, size, _modified) in entries {
-        let encoded_name = url_encode(&name);
-        let full_url = if request_path.ends_with('/') {
-            format!("{}{}", request_path, encoded_name)
-        } else {
-            format!("{}/{}", request_path, encoded_name)
-        };
 
-        let class = if is_dir { "dir" } else { "file" };
-        let size_display = match size {
-            Some(s) if s == 0 => "-".to_string(),
-            Some(s) => format!("{} B", s),
-            None => "-".to_string(),
-        };
+    #[tokio::test]
+    async fn serves_files_inside_root() {
+        let (_dir, root) = fixture();
+        let root = root.canonicalize().unwrap();
+        assert_eq!(resolve(&root, "/index.html").await.unwrap(), root.join("index.html"));
+        assert_eq!(resolve(&root, "/sub/page.html").await.unwrap(), root.join("sub/page.html"));
+        assert_eq!(resolve(&root, "/./sub//page.html").await.unwrap(), root.join("sub/page.html"));
+        assert_eq!(resolve(&root, "/sub%2Fpage.html").await.unwrap(), root.join("sub/page.html"));
+    }
 
-        let mod_display = "-";
+    #[tokio::test]
+    async fn rejects_traversal_payloads() {
+        let (_dir, root) = fixture();
+        let payloads = [
+            "/../secret.txt",
+            "/sub/../../secret.txt",
+            "/sub/../index.html",
+            "/..",
+            "/%2e%2e/secret.txt",
+            "/%2E%2E/secret.txt",
+            "/.%2e/secret.txt",
+            "/%2e./secret.txt",
+            "/sub/%2e%2e/%2e%2e/secret.txt",
+            "/..%2fsecret.txt",
+            "/%2e%2e%2fsecret.txt",
+            "/..%5csecret.txt",
+            "/%5c..%5csecret.txt",
+            "/sub%5c..%5c..%5csecret.txt",
+            "/index.html%00.png",
+            "/%00",
+        ];
+        for payload in payloads {
+            let err = resolve(&root, payload).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput, "payload {} was not rejected", payload);
+        }
+    }
 
-        html.push_str(&format!(
-            r#"<tr><td><a href="{}" class="{}">{}</a></td><td class="size">{}</td><td>{}</td></tr>"#,
-            full_url,
-            class,
-            name,
-            size_display,
-            mod_display
-        ));
+    #[tokio::test]
+    async fn double_encoding_is_not_decoded_twice() {
+        let (_dir, root) = fixture();
+        for payload in ["/%252e%252e/secret.txt", "/..%252fsecret.txt"] {
+            let err = resolve(&root, payload).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound, "payload {}", payload);
+        }
     }
 
-    html.push_str("</tbody></table></body></html>");
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn rejects_symlinks_leaving_root() {
+        let (_dir, root) = fixture();
+        for payload in ["/escape.txt", "/escape-dir/secret.txt", "/escape-dir"] {
+            let err = resolve(&root, payload).await.unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::PermissionDenied, "payload {}", payload);
+        }
+        let root = root.canonicalize().unwrap();
+        assert_eq!(resolve(&root, "/alias.html").await.unwrap(), root.join("sub/page.html"));
+    }
 
-    send_response(
-        stream,
-        "200 OK",
-        html.as_bytes(),
-        "text/html; charset=utf-8",
-        Some("Cache-Control: no-cache"),
-    )
-}
-fn url_encode(s: &str) -> String {
-    s.chars()
-        .map(|c| {
-            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' || c == '~' {
-                c.to_string()
-            } else {
-                format!("%{:02X}", c as u8)
-            }
-        })
-        .collect()
+    #[tokio::test]
+    async fn missing_files_are_not_found() {
+        let (_dir, root) = fixture();
+        for payload in ["/missing.html", "/index.html/child", "/sub/missing/page.html"] {
+            let err = resolve(&root, payload).await.unwrap_err();
+            assert!(
+                matches!(err.kind(), ErrorKind::NotFound | ErrorKind::NotADirectory),
+                "payload {}: {:?}",
+                payload,
+                err
+            );
+        }
+    }
 }
-*/
\ No newline at end of file